@@ -0,0 +1,74 @@
+use super::film::PixelStatistics;
+
+/// Sampling that keeps refining the noisy pixels until their estimated noise is low enough.
+///
+/// Every pixel starts with `min_samples`, then passes double the samples of the pixels whose
+/// relative noise is above `threshold`, or next to one, until `max_samples` is reached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Standard error of the pixel mean relative to the mean, 0.01 is about 1% of noise.
+    pub threshold: f64,
+    pub min_samples: u32,
+    pub max_samples: u32,
+}
+
+impl AdaptiveSampling {
+    pub fn new(threshold: f64) -> AdaptiveSampling {
+        AdaptiveSampling {
+            threshold,
+            ..AdaptiveSampling::default()
+        }
+    }
+
+    pub fn with_threshold(&self, threshold: f64) -> AdaptiveSampling {
+        AdaptiveSampling { threshold, ..*self }
+    }
+
+    pub fn with_min_samples(&self, min_samples: u32) -> AdaptiveSampling {
+        AdaptiveSampling {
+            min_samples,
+            ..*self
+        }
+    }
+
+    pub fn with_max_samples(&self, max_samples: u32) -> AdaptiveSampling {
+        AdaptiveSampling {
+            max_samples,
+            ..*self
+        }
+    }
+
+    /// Pixels that need more samples. The estimate of a single pixel is noisy itself, so the
+    /// neighbours of a noisy pixel are refined as well.
+    pub fn noisy_pixels(
+        &self,
+        statistics: &[PixelStatistics],
+        width: u32,
+        height: u32,
+    ) -> Vec<bool> {
+        let noisy = statistics
+            .iter()
+            .map(|pixel| pixel.relative_error() > self.threshold)
+            .collect::<Vec<_>>();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let rows = y.saturating_sub(1)..=(y + 1).min(height - 1);
+                rows.into_iter().any(|y| {
+                    (x.saturating_sub(1)..=(x + 1).min(width - 1))
+                        .any(|x| noisy[(y * width + x) as usize])
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> AdaptiveSampling {
+        AdaptiveSampling {
+            threshold: 0.02,
+            min_samples: 16,
+            max_samples: 1024,
+        }
+    }
+}
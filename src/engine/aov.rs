@@ -0,0 +1,308 @@
+use image::{Rgb, RgbImage};
+
+use crate::utils::{color::Color, math::vector::Vector};
+
+use super::{
+    film::{Film, FilmTile},
+    filter::Filter,
+    integrator::RadianceSplit,
+    intersection::Intersection,
+    sampler::hash,
+    scene::Scene,
+    tone_mapping::ToneMapper,
+};
+
+/// Arbitrary output variable, an image rendered along the beauty for compositing and denoising.
+///
+/// The surface AOVs describe the first surface seen by the camera samples and are filtered like
+/// the beauty so that they line up with it, they are 0 where nothing is hit. Ids are not averaged:
+/// each pixel keeps the ids of its sample closest to its center, -1 for the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Aov {
+    /// Distance from the camera to the surface.
+    Depth,
+    /// World space normal of the surface.
+    Normal,
+    /// World space normal used for shading, interpolated on smooth triangles.
+    ShadingNormal,
+    /// Color of the material.
+    Albedo,
+    /// Texture coordinates.
+    Uv,
+    /// Index of the object in the scene.
+    ObjectId,
+    /// Index of the texture of the surface in the scene, surfaces with equal textures share it.
+    MaterialId,
+    /// Light split by path, see `RadianceSplit`.
+    Direct,
+    Indirect,
+    Specular,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 10] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::ShadingNormal,
+        Aov::Albedo,
+        Aov::Uv,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::Specular,
+    ];
+
+    /// Name of the EXR layer and suffix of the image files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::ShadingNormal => "shading_normal",
+            Aov::Albedo => "albedo",
+            Aov::Uv => "uv",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Specular => "specular",
+        }
+    }
+
+    /// Names of the channels, stored in the red, green and blue components of the pixels.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::Normal | Aov::ShadingNormal => &["X", "Y", "Z"],
+            Aov::Uv => &["U", "V"],
+            Aov::ObjectId | Aov::MaterialId => &["id"],
+            Aov::Albedo | Aov::Direct | Aov::Indirect | Aov::Specular => &["R", "G", "B"],
+        }
+    }
+
+    pub fn is_id(&self) -> bool {
+        matches!(self, Aov::ObjectId | Aov::MaterialId)
+    }
+
+    pub fn value(&self, sample: &AovSample) -> Color {
+        let vector = |vector: Vector| Color::new(vector.x, vector.y, vector.z);
+        let id = |id: Option<u32>| {
+            let id = id.map_or(-1.0, |id| id as f64);
+            Color::new(id, id, id)
+        };
+        match self {
+            Aov::Depth => Color::new(sample.depth, sample.depth, sample.depth),
+            Aov::Normal => vector(sample.normal),
+            Aov::ShadingNormal => vector(sample.shading_normal),
+            Aov::Albedo => sample.albedo,
+            Aov::Uv => Color::new(sample.uv.0, sample.uv.1, 0.0),
+            Aov::ObjectId => id(sample.object),
+            Aov::MaterialId => id(sample.material),
+            Aov::Direct => sample.radiance.direct,
+            Aov::Indirect => sample.radiance.indirect,
+            Aov::Specular => sample.radiance.specular,
+        }
+    }
+
+    /// 8-bit preview of the AOV held in `film`. Light goes through `tone_mapper`, depth fades from
+    /// white near the camera, normals are mapped to colors, UVs are wrapped and ids get random
+    /// colors.
+    pub fn to_rgb_image(&self, film: &Film, tone_mapper: &ToneMapper) -> RgbImage {
+        let byte = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        // Scaled by the median so that far away surfaces like planes don't flatten it
+        let mut depths = film
+            .pixels
+            .iter()
+            .map(|pixel| pixel.r)
+            .filter(|depth| *depth > 0.0)
+            .collect::<Vec<_>>();
+        depths.sort_by(f64::total_cmp);
+        let far = depths
+            .get(depths.len() / 2)
+            .map_or(1.0, |median| median * 2.0);
+        let preview = |color: Color| match self {
+            Aov::Depth if color.r > 0.0 => Rgb([byte(1.0 - 0.9 * (color.r / far).min(1.0)); 3]),
+            Aov::Normal | Aov::ShadingNormal if color != Color::zero() => Rgb([
+                byte(color.r * 0.5 + 0.5),
+                byte(color.g * 0.5 + 0.5),
+                byte(color.b * 0.5 + 0.5),
+            ]),
+            Aov::Uv => Rgb([
+                byte(color.r.rem_euclid(1.0)),
+                byte(color.g.rem_euclid(1.0)),
+                0,
+            ]),
+            Aov::ObjectId | Aov::MaterialId if color.r >= 0.0 => {
+                let bits = hash(&[color.r as u64]).to_le_bytes();
+                Rgb([bits[0], bits[1], bits[2]])
+            }
+            _ => Rgb([0; 3]),
+        };
+
+        match self {
+            Aov::Albedo => film.to_rgb_image(&ToneMapper::default()),
+            Aov::Direct | Aov::Indirect | Aov::Specular => film.to_rgb_image(tone_mapper),
+            _ => RgbImage::from_fn(film.width, film.height, |x, y| {
+                preview(film.get_pixel(x, y))
+            }),
+        }
+    }
+}
+
+/// Values of the AOVs for one camera sample.
+#[derive(Debug, Clone, Copy)]
+pub struct AovSample {
+    pub depth: f64,
+    pub normal: Vector,
+    pub shading_normal: Vector,
+    pub albedo: Color,
+    pub uv: (f64, f64),
+    pub object: Option<u32>,
+    pub material: Option<u32>,
+    pub radiance: RadianceSplit,
+}
+
+impl AovSample {
+    /// Describes the first surface hit by a camera ray in `scene`, the index of its object and its
+    /// intersection as returned by `Integrator::radiance_split`, along with the light `radiance`.
+    pub fn new(
+        scene: &Scene,
+        hit: Option<&(usize, Intersection)>,
+        radiance: RadianceSplit,
+    ) -> AovSample {
+        match hit {
+            Some((object, intersection)) => AovSample {
+                depth: intersection.distance,
+                normal: intersection.geometric_normal,
+                shading_normal: intersection.normal,
+                albedo: intersection.material.color,
+                uv: intersection.uv,
+                object: Some(*object as u32),
+                material: scene.material_id(*object, intersection),
+                radiance,
+            },
+            None => AovSample::miss(radiance),
+        }
+    }
+
+    /// Sample of a ray hitting nothing.
+    pub fn miss(radiance: RadianceSplit) -> AovSample {
+        let zero = Vector::new(0.0, 0.0, 0.0);
+        AovSample {
+            depth: 0.0,
+            normal: zero,
+            shading_normal: zero,
+            albedo: Color::zero(),
+            uv: (0.0, 0.0),
+            object: None,
+            material: None,
+            radiance,
+        }
+    }
+}
+
+/// AOV samples landing around a rectangle of the image, like `FilmTile` for the beauty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AovTile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub aovs: Vec<Aov>,
+    /// One film tile per AOV, ids have a weight of 1 where a sample was kept.
+    pub layers: Vec<FilmTile>,
+    /// Squared distance from the pixel centers to the samples whose ids are kept.
+    pub nearest: Vec<f64>,
+}
+
+impl AovTile {
+    pub fn new(aovs: &[Aov], x: u32, y: u32, width: u32, height: u32) -> AovTile {
+        AovTile {
+            x,
+            y,
+            width,
+            height,
+            aovs: aovs.to_vec(),
+            layers: vec![FilmTile::new(x, y, width, height); aovs.len()],
+            nearest: vec![f64::INFINITY; (width * height) as usize],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aovs.is_empty()
+    }
+
+    /// Adds a sample taken at `position` in image coordinates.
+    pub fn add_sample(&mut self, position: (f64, f64), sample: &AovSample, filter: &Filter) {
+        for (aov, layer) in self.aovs.iter().zip(self.layers.iter_mut()) {
+            if !aov.is_id() {
+                layer.add_sample(position, aov.value(sample), filter);
+            }
+        }
+
+        let (x, y) = (position.0.floor(), position.1.floor());
+        let (left, top) = (self.x as f64, self.y as f64);
+        if x < left || y < top || x >= left + self.width as f64 || y >= top + self.height as f64 {
+            return;
+        }
+        let index = ((y - top) as u32 * self.width + (x - left) as u32) as usize;
+        let distance = (position.0 - x - 0.5).powi(2) + (position.1 - y - 0.5).powi(2);
+        if distance < self.nearest[index] {
+            self.nearest[index] = distance;
+            for (aov, layer) in self.aovs.iter().zip(self.layers.iter_mut()) {
+                if aov.is_id() {
+                    layer.sums[index] = aov.value(sample);
+                    layer.weights[index] = 1.0;
+                }
+            }
+        }
+    }
+
+    /// Adds the samples of `other` where the tiles overlap, keeping the closest ids.
+    pub fn merge(&mut self, other: &AovTile) {
+        for ((aov, layer), other_layer) in self.aovs.iter().zip(&mut self.layers).zip(&other.layers)
+        {
+            if !aov.is_id() {
+                layer.merge(other_layer);
+            }
+        }
+
+        let (x_start, x_end) = (
+            self.x.max(other.x),
+            (self.x + self.width).min(other.x + other.width),
+        );
+        let (y_start, y_end) = (
+            self.y.max(other.y),
+            (self.y + self.height).min(other.y + other.height),
+        );
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                let index = ((y - self.y) * self.width + x - self.x) as usize;
+                let other_index = ((y - other.y) * other.width + x - other.x) as usize;
+                if other.nearest[other_index] < self.nearest[index] {
+                    self.nearest[index] = other.nearest[other_index];
+                    for ((aov, layer), other_layer) in
+                        self.aovs.iter().zip(&mut self.layers).zip(&other.layers)
+                    {
+                        if aov.is_id() {
+                            layer.sums[index] = other_layer.sums[other_index];
+                            layer.weights[index] = other_layer.weights[other_index];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Writes the normalized pixels of the tile into the AOVs of `film`.
+    pub fn develop(&self, film: &mut Film) {
+        let (width, height) = (film.width, film.height);
+        for (aov, layer) in self.aovs.iter().zip(&self.layers) {
+            let aov_film = film
+                .aovs
+                .entry(*aov)
+                .or_insert_with(|| Film::new(width, height));
+            layer.develop(aov_film);
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{objects::Intersectable, utils::math::point::Point};
+use crate::{
+    objects::Intersectable,
+    utils::math::{point::Point, transform::Transform, vector::Vector},
+};
 
 #[derive(Debug, Clone)]
 pub struct BoundingBox {
@@ -29,29 +32,32 @@ impl BoundingBox {
             && self.min.z <= other.max.z
     }
 
-    pub fn intersects_ray(&self, origin: &Point, direction: &Point) -> bool {
-        let mut tmin = (self.min.x - origin.x) / direction.x;
-        let mut tmax = (self.max.x - origin.x) / direction.x;
-
-        let tymin = (self.min.y - origin.y) / direction.y;
-        let tymax = (self.max.y - origin.y) / direction.y;
-
-        if (tmin > tymax) || (tymin > tmax) {
-            return false;
-        }
+    pub fn intersects_ray(&self, origin: &Point, direction: &Vector) -> bool {
+        self.hit_distance(origin, direction).is_some()
+    }
 
-        if tymin > tmin {
-            tmin = tymin;
+    /// Slab test returning the distance at which the ray enters the box (0 if it starts inside).
+    pub fn hit_distance(&self, origin: &Point, direction: &Vector) -> Option<f64> {
+        let mut tmin = 0.0_f64;
+        let mut tmax = f64::INFINITY;
+
+        for (min, max, origin, direction) in [
+            (self.min.x, self.max.x, origin.x, direction.x),
+            (self.min.y, self.max.y, origin.y, direction.y),
+            (self.min.z, self.max.z, origin.z, direction.z),
+        ] {
+            let inv = 1.0 / direction;
+            let t1 = (min - origin) * inv;
+            let t2 = (max - origin) * inv;
+            tmin = tmin.max(t1.min(t2));
+            tmax = tmax.min(t1.max(t2));
         }
 
-        if tymax < tmax {
-            tmax = tymax;
+        if tmin <= tmax {
+            Some(tmin)
+        } else {
+            None
         }
-
-        let tzmin = (self.min.z - origin.z) / direction.z;
-        let tzmax = (self.max.z - origin.z) / direction.z;
-
-        !((tmin > tzmax) || (tzmin > tmax))
     }
 
     pub fn union(&self, other: &BoundingBox) -> BoundingBox {
@@ -107,10 +113,67 @@ impl BoundingBox {
         }
     }
 
+    pub fn is_finite(&self) -> bool {
+        self.min.x.is_finite()
+            && self.min.y.is_finite()
+            && self.min.z.is_finite()
+            && self.max.x.is_finite()
+            && self.max.y.is_finite()
+            && self.max.z.is_finite()
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let size = self.size();
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    pub fn largest_axis(&self) -> usize {
+        let size = self.size();
+        if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
     }
 
+    /// Box enclosing the transformed corners, infinite boxes stay infinite.
+    pub fn transform(&self, transform: &Transform) -> BoundingBox {
+        if self.is_empty() {
+            return BoundingBox::empty();
+        }
+        if !self.is_finite() {
+            return BoundingBox::new(
+                Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+                Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            );
+        }
+        let corners = (0..8)
+            .map(|i| {
+                transform.point(&Point::new(
+                    if i & 1 == 0 { self.min.x } else { self.max.x },
+                    if i & 2 == 0 { self.min.y } else { self.max.y },
+                    if i & 4 == 0 { self.min.z } else { self.max.z },
+                ))
+            })
+            .collect::<Vec<_>>();
+        BoundingBox::from_points(&corners)
+    }
+
+    pub fn from_points(points: &[Point]) -> BoundingBox {
+        points
+            .iter()
+            .fold(BoundingBox::empty(), |bbox, point| bbox.union_point(point))
+    }
+
     pub fn from_objects(objects: &[crate::objects::Object]) -> BoundingBox {
         let mut bbox = BoundingBox::empty();
 
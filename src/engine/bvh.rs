@@ -0,0 +1,406 @@
+use crate::utils::math::point::Point;
+
+use super::{bounding_box::BoundingBox, intersection::Intersection, ray::Ray};
+
+const BUCKET_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+const MAX_SAH_LEAF_SIZE: usize = 16;
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Debug, Clone)]
+pub struct BvhNode {
+    pub bounding_box: BoundingBox,
+    /// For a leaf, index of the first primitive in `Bvh::indices`.
+    /// For an interior node, index of the second child (the first child always follows its parent).
+    pub offset: usize,
+    /// Number of primitives in a leaf, 0 for interior nodes.
+    pub count: usize,
+    pub axis: usize,
+}
+
+impl BvhNode {
+    pub fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// Bounding volume hierarchy built with the surface area heuristic and flattened in depth-first order.
+///
+/// The hierarchy only stores indices, so the same structure is used for the objects of a scene and
+/// for the faces of a mesh. Primitives with an infinite bounding box (planes) cannot be partitioned
+/// and are kept apart in `unbounded`.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    pub nodes: Vec<BvhNode>,
+    pub indices: Vec<usize>,
+    pub unbounded: Vec<usize>,
+}
+
+struct BuildPrimitive {
+    index: usize,
+    bounding_box: BoundingBox,
+    centroid: Point,
+}
+
+impl Bvh {
+    pub fn new(bounding_boxes: &[BoundingBox]) -> Bvh {
+        let mut unbounded = Vec::new();
+        let mut primitives = Vec::new();
+
+        for (index, bounding_box) in bounding_boxes.iter().enumerate() {
+            if bounding_box.is_empty() {
+                continue;
+            }
+            if !bounding_box.is_finite() {
+                unbounded.push(index);
+                continue;
+            }
+            primitives.push(BuildPrimitive {
+                index,
+                bounding_box: bounding_box.clone(),
+                centroid: bounding_box.center(),
+            });
+        }
+
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(primitives.len() * 2),
+            indices: Vec::with_capacity(primitives.len()),
+            unbounded,
+        };
+
+        if !primitives.is_empty() {
+            bvh.build(&mut primitives);
+        }
+
+        bvh
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        self.nodes
+            .first()
+            .map(|node| node.bounding_box.clone())
+            .unwrap_or_else(BoundingBox::empty)
+    }
+
+    fn build(&mut self, primitives: &mut [BuildPrimitive]) -> usize {
+        let bounding_box = primitives
+            .iter()
+            .fold(BoundingBox::empty(), |bbox, p| bbox.union(&p.bounding_box));
+
+        let node_index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounding_box,
+            offset: 0,
+            count: 0,
+            axis: 0,
+        });
+
+        let centroid_bounds = primitives.iter().fold(BoundingBox::empty(), |bbox, p| {
+            bbox.union_point(&p.centroid)
+        });
+        let axis = centroid_bounds.largest_axis();
+        let extent = axis_value(&centroid_bounds.size(), axis);
+
+        let split = if primitives.len() <= MAX_LEAF_SIZE || extent <= 0.0 {
+            None
+        } else {
+            self.find_split(primitives, &centroid_bounds, axis)
+        };
+
+        match split {
+            None => {
+                self.nodes[node_index].offset = self.indices.len();
+                self.nodes[node_index].count = primitives.len();
+                self.indices.extend(primitives.iter().map(|p| p.index));
+            }
+            Some(mid) => {
+                let (left, right) = primitives.split_at_mut(mid);
+                self.build(left);
+                let second_child = self.build(right);
+                self.nodes[node_index].offset = second_child;
+                self.nodes[node_index].axis = axis;
+            }
+        }
+
+        node_index
+    }
+
+    /// Partitions `primitives` along `axis` and returns the split position, or `None` if a leaf is cheaper.
+    fn find_split(
+        &self,
+        primitives: &mut [BuildPrimitive],
+        centroid_bounds: &BoundingBox,
+        axis: usize,
+    ) -> Option<usize> {
+        let min = axis_value(&centroid_bounds.min, axis);
+        let extent = axis_value(&centroid_bounds.size(), axis);
+        let bucket_of = |p: &BuildPrimitive| {
+            let offset = (axis_value(&p.centroid, axis) - min) / extent;
+            ((offset * BUCKET_COUNT as f64) as usize).min(BUCKET_COUNT - 1)
+        };
+
+        let mut counts = [0usize; BUCKET_COUNT];
+        let mut boxes = vec![BoundingBox::empty(); BUCKET_COUNT];
+        for p in primitives.iter() {
+            let bucket = bucket_of(p);
+            counts[bucket] += 1;
+            boxes[bucket] = boxes[bucket].union(&p.bounding_box);
+        }
+
+        // Sweep from both sides so that every split cost is computed in linear time
+        let mut below_area = [0.0; BUCKET_COUNT - 1];
+        let mut below_count = [0usize; BUCKET_COUNT - 1];
+        let mut bbox = BoundingBox::empty();
+        let mut count = 0;
+        for i in 0..BUCKET_COUNT - 1 {
+            bbox = bbox.union(&boxes[i]);
+            count += counts[i];
+            below_area[i] = bbox.surface_area();
+            below_count[i] = count;
+        }
+
+        let mut best = None;
+        let mut best_cost = f64::INFINITY;
+        let mut bbox = BoundingBox::empty();
+        let mut count = 0;
+        for i in (1..BUCKET_COUNT).rev() {
+            bbox = bbox.union(&boxes[i]);
+            count += counts[i];
+            if count == 0 || below_count[i - 1] == 0 {
+                continue;
+            }
+            let cost =
+                below_area[i - 1] * below_count[i - 1] as f64 + bbox.surface_area() * count as f64;
+            if cost < best_cost {
+                best_cost = cost;
+                best = Some(i);
+            }
+        }
+
+        let parent_area = primitives
+            .iter()
+            .fold(BoundingBox::empty(), |bbox, p| bbox.union(&p.bounding_box))
+            .surface_area();
+        let split_cost = TRAVERSAL_COST + best_cost / parent_area;
+        let leaf_cost = primitives.len() as f64;
+
+        let bucket = best?;
+        if split_cost >= leaf_cost && primitives.len() <= MAX_SAH_LEAF_SIZE {
+            return None;
+        }
+
+        let mut mid = 0;
+        for i in 0..primitives.len() {
+            if bucket_of(&primitives[i]) < bucket {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+
+        Some(mid)
+    }
+
+    /// Finds the closest hit along `ray`, calling `intersect` with the index of every candidate primitive.
+    pub fn intersect<F>(&self, ray: &Ray, mut intersect: F) -> Option<Intersection>
+    where
+        F: FnMut(usize) -> Option<Intersection>,
+    {
+        self.nearest(ray, |index| {
+            intersect(index).map(|inter| (inter.distance, inter))
+        })
+        .map(|(_, inter)| inter)
+    }
+
+    /// Like `intersect` but `hit` only returns a distance and some data, so that building the
+    /// intersection can be deferred to the closest hit.
+    pub fn nearest<T, F>(&self, ray: &Ray, mut hit: F) -> Option<(f64, T)>
+    where
+        F: FnMut(usize) -> Option<(f64, T)>,
+    {
+        let mut closest: Option<(f64, T)> = None;
+        let mut consider = |index: usize, closest: &mut Option<(f64, T)>| {
+            if let Some((distance, data)) = hit(index) {
+                if closest.as_ref().is_none_or(|c| distance < c.0) {
+                    *closest = Some((distance, data));
+                }
+            }
+        };
+
+        for &index in self.unbounded.iter() {
+            consider(index, &mut closest);
+        }
+
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let negative = [
+            ray.direction.x < 0.0,
+            ray.direction.y < 0.0,
+            ray.direction.z < 0.0,
+        ];
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let max_distance = closest.as_ref().map_or(f64::INFINITY, |c| c.0);
+            match node.bounding_box.hit_distance(&ray.origin, &ray.direction) {
+                Some(distance) if distance <= max_distance => {}
+                _ => continue,
+            }
+
+            if node.is_leaf() {
+                for &index in &self.indices[node.offset..node.offset + node.count] {
+                    consider(index, &mut closest);
+                }
+            } else if negative[node.axis] {
+                stack.push(node_index + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(node_index + 1);
+            }
+        }
+
+        closest
+    }
+
+    /// Whether `hit` returns true for a primitive whose bounding box is closer than
+    /// `max_distance` along `ray`. Stops at the first one, so it's cheaper than `nearest` for
+    /// shadow rays.
+    pub fn any_hit<F>(&self, ray: &Ray, max_distance: f64, mut hit: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        if self.unbounded.iter().any(|&index| hit(index)) {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            match node.bounding_box.hit_distance(&ray.origin, &ray.direction) {
+                Some(distance) if distance < max_distance => {}
+                _ => continue,
+            }
+
+            if node.is_leaf() {
+                let indices = &self.indices[node.offset..node.offset + node.count];
+                if indices.iter().any(|&index| hit(index)) {
+                    return true;
+                }
+            } else {
+                stack.push(node.offset);
+                stack.push(node_index + 1);
+            }
+        }
+
+        false
+    }
+}
+
+fn axis_value(point: &Point, axis: usize) -> f64 {
+    match axis {
+        0 => point.x,
+        1 => point.y,
+        _ => point.z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::texture::Texture,
+        objects::{plane::Plane, sphere::Sphere, Intersectable, Object},
+        utils::math::vector::Vector,
+    };
+
+    /// Xorshift generator, enough to scatter test geometry reproducibly.
+    fn generator() -> impl FnMut() -> f64 {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        }
+    }
+
+    fn scene() -> Vec<Object> {
+        let mut random = generator();
+        let mut objects = (0..200)
+            .map(|_| {
+                let center = Point::new(
+                    random() * 20.0 - 10.0,
+                    random() * 20.0 - 10.0,
+                    random() * 20.0 - 10.0,
+                );
+                Sphere::new(center, 0.2 + random(), Texture::default()).into()
+            })
+            .collect::<Vec<Object>>();
+        let floor = Plane::new(
+            Point::new(0.0, -8.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Texture::default(),
+            1.0,
+        );
+        objects.push((&floor).into());
+        objects
+    }
+
+    fn rays() -> Vec<Ray> {
+        let mut random = generator();
+        let mut coordinate = move |scale: f64| (random() * 2.0 - 1.0) * scale;
+        (0..500)
+            .map(|_| {
+                let origin = Point::new(coordinate(15.0), coordinate(15.0), coordinate(15.0));
+                let direction = Vector::new(coordinate(1.0), coordinate(1.0), coordinate(1.0));
+                Ray::new(origin, direction, 0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn closest_hit_matches_brute_force() {
+        let objects = scene();
+        let boxes = objects.iter().map(|o| o.bounding_box()).collect::<Vec<_>>();
+        let bvh = Bvh::new(&boxes);
+
+        for ray in rays() {
+            let expected = objects
+                .iter()
+                .filter_map(|object| object.intersect(&ray))
+                .map(|intersection| intersection.distance)
+                .min_by(f64::total_cmp);
+            let found = bvh
+                .intersect(&ray, |index| objects[index].intersect(&ray))
+                .map(|intersection| intersection.distance);
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn any_hit_matches_brute_force() {
+        let objects = scene();
+        let boxes = objects.iter().map(|o| o.bounding_box()).collect::<Vec<_>>();
+        let bvh = Bvh::new(&boxes);
+
+        for (ray, max_distance) in rays().into_iter().zip([1.0, 5.0, 20.0].into_iter().cycle()) {
+            let expected = objects.iter().any(|object| {
+                object
+                    .intersect(&ray)
+                    .is_some_and(|intersection| intersection.distance < max_distance)
+            });
+            let found = bvh.any_hit(&ray, max_distance, |index| {
+                objects[index].hits(&ray, max_distance)
+            });
+            assert_eq!(found, expected);
+        }
+    }
+}
@@ -1,23 +1,61 @@
-use crate::utils::math::{point::Point, vector::Vector};
+use std::f64::consts::{FRAC_PI_2, PI};
 
-use super::ray::Ray;
-use rand::Rng;
+use crate::utils::math::{concentric_disk, point::Point, vector::Vector};
+
+use super::{
+    adaptive::AdaptiveSampling,
+    filter::Filter,
+    integrator::Integrator,
+    projection::Projection,
+    ray::Ray,
+    sampler::{PixelSampler, Sampler},
+    scene::Scene,
+};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
 #[derive(Debug, Clone)]
 pub struct Camera {
     pub position: Point,
+    /// `direction`, `up` and `right` form an orthonormal basis, they are derived from the
+    /// direction, `world_up` and `roll` by the builder methods.
     pub direction: Vector,
     pub up: Vector,
     pub right: Vector,
+    /// Up vector the camera is aligned with, it does not have to be perpendicular to `direction`.
+    pub world_up: Vector,
+    /// Rotation around the view direction in degrees, counter-clockwise.
+    pub roll: f64,
+    /// Tangent of half the horizontal field of view, ignored when `auto_aspect` is set.
     pub width_fov: f64,
+    /// Tangent of half the vertical field of view.
     pub height_fov: f64,
+    /// Derives the horizontal field of view from the vertical one and the image size.
+    pub auto_aspect: bool,
     pub z_min: f64,
     pub max_bounces: u32,
+    /// Samples per pixel, unless sampling is adaptive.
     pub anti_aliasing: u32,
+    /// Filter the samples are weighted by when they are splatted on the neighbouring pixels.
+    pub filter: Filter,
+    /// Keeps sampling noisy pixels up to a noise threshold instead of taking `anti_aliasing`
+    /// samples everywhere.
+    pub adaptive: Option<AdaptiveSampling>,
+    pub integrator: Integrator,
+    /// Numbers driving the pixel, lens, light and bounce sampling of every camera ray.
+    pub sampler: Sampler,
+    pub projection: Projection,
+    /// Radius of the lens, 0 for a pinhole camera with everything in focus.
+    pub aperture: f64,
+    /// Distance along `direction` of the plane in focus.
+    pub focus_distance: f64,
+    /// Number of diaphragm blades, giving polygonal bokeh. Below 3 the aperture is round.
+    pub blades: u32,
+    /// Rotation of the blades in degrees.
+    pub blade_rotation: f64,
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         position: Point,
         direction: Vector,
@@ -28,19 +66,60 @@ impl Camera {
         max_bounces: u32,
         anti_aliasing: u32,
     ) -> Camera {
-        let right = direction.cross(&up);
         let height_fov = (beta.to_radians() / 2.0).tan();
         let width_fov = (alpha.to_radians() / 2.0).tan();
         Camera {
             position,
             direction,
             up,
-            right,
+            right: Vector::zero(),
+            world_up: up,
+            roll: 0.0,
             width_fov,
             height_fov,
+            auto_aspect: false,
             z_min,
             max_bounces,
             anti_aliasing,
+            filter: Filter::default(),
+            adaptive: None,
+            integrator: Integrator::default(),
+            sampler: Sampler::default(),
+            projection: Projection::default(),
+            aperture: 0.0,
+            focus_distance: 10.0,
+            blades: 0,
+            blade_rotation: 0.0,
+        }
+        .with_basis()
+    }
+
+    /// Camera at `eye` looking at `target`, with a vertical field of view and an aspect ratio
+    /// following the image.
+    pub fn look_at(eye: Point, target: Point, up: Vector) -> Camera {
+        Camera::default()
+            .with_position(eye)
+            .with_up(up)
+            .with_target(target)
+    }
+
+    /// Recomputes `direction`, `up` and `right` from the direction, `world_up` and `roll`.
+    fn with_basis(&self) -> Self {
+        let direction = self.direction.normalize();
+        let right = direction.cross(&self.world_up);
+        // Looking along the up vector, any perpendicular vector will do
+        let right = if right.length_squared() < 1e-12 {
+            direction.orthonormal_basis().0
+        } else {
+            right.normalize()
+        };
+        let up = right.cross(&direction);
+        let (sin, cos) = self.roll.to_radians().sin_cos();
+        Self {
+            direction,
+            up: up * cos - right * sin,
+            right: right * cos + up * sin,
+            ..self.clone()
         }
     }
 
@@ -52,31 +131,44 @@ impl Camera {
     }
 
     pub fn with_direction(&self, direction: Vector) -> Self {
-        let right = direction.cross(&self.up);
         Self {
             direction,
-            right,
             ..self.clone()
         }
+        .with_basis()
+    }
+
+    pub fn with_target(&self, target: Point) -> Self {
+        self.with_direction(target - self.position)
     }
 
     pub fn with_up(&self, up: Vector) -> Self {
-        let right = self.direction.cross(&up);
         Self {
-            up,
-            right,
+            world_up: up,
+            ..self.clone()
+        }
+        .with_basis()
+    }
+
+    pub fn with_roll(&self, roll: f64) -> Self {
+        Self {
+            roll,
             ..self.clone()
         }
+        .with_basis()
     }
 
+    /// Sets the horizontal field of view in degrees, fixing the aspect ratio.
     pub fn with_alpha(&self, alpha: f64) -> Self {
         let width_fov = (alpha.to_radians() / 2.0).tan();
         Self {
             width_fov,
+            auto_aspect: false,
             ..self.clone()
         }
     }
 
+    /// Sets the vertical field of view in degrees.
     pub fn with_beta(&self, beta: f64) -> Self {
         let height_fov = (beta.to_radians() / 2.0).tan();
         Self {
@@ -85,6 +177,21 @@ impl Camera {
         }
     }
 
+    /// Sets the vertical field of view in degrees, the horizontal one follows the image size.
+    pub fn with_vertical_fov(&self, fov: f64) -> Self {
+        Self {
+            auto_aspect: true,
+            ..self.with_beta(fov)
+        }
+    }
+
+    pub fn with_auto_aspect(&self, auto_aspect: bool) -> Self {
+        Self {
+            auto_aspect,
+            ..self.clone()
+        }
+    }
+
     pub fn with_z_min(&self, z_min: f64) -> Self {
         Self {
             z_min,
@@ -106,7 +213,101 @@ impl Camera {
         }
     }
 
-    fn ray_inner(&self, coord: (f64, f64), offset: (f64, f64), image_size: (usize, usize)) -> Ray {
+    pub fn with_filter(&self, filter: Filter) -> Self {
+        Self {
+            filter,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_adaptive(&self, adaptive: AdaptiveSampling) -> Self {
+        Self {
+            adaptive: Some(adaptive),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_integrator(&self, integrator: Integrator) -> Self {
+        Self {
+            integrator,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_sampler(&self, sampler: Sampler) -> Self {
+        Self {
+            sampler,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_projection(&self, projection: Projection) -> Self {
+        Self {
+            projection,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_aperture(&self, aperture: f64) -> Self {
+        Self {
+            aperture,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_focus_distance(&self, focus_distance: f64) -> Self {
+        Self {
+            focus_distance,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_blades(&self, blades: u32, blade_rotation: f64) -> Self {
+        Self {
+            blades,
+            blade_rotation,
+            ..self.clone()
+        }
+    }
+
+    /// Focuses on the first object hit through the center of the image, the focus distance is
+    /// kept if nothing is hit.
+    pub fn with_autofocus(&self, scene: &Scene) -> Self {
+        let ray = Ray::new(self.position, self.direction, 0);
+        match scene.trace(ray) {
+            Some(intersection) => self.with_focus_distance(intersection.distance),
+            None => self.clone(),
+        }
+    }
+
+    /// Point of the aperture for two uniform numbers in [0, 1), relative to the lens center.
+    fn lens_point(&self, u: f64, v: f64) -> (f64, f64) {
+        let (x, y) = if self.blades < 3 {
+            concentric_disk(u, v)
+        } else {
+            // Uniform point in one of the triangles fanning the polygon from its center
+            let blades = self.blades as f64;
+            let blade = (u * blades).floor().min(blades - 1.0);
+            let u = u * blades - blade;
+            let angle = std::f64::consts::TAU / blades;
+            let start = blade * angle + self.blade_rotation.to_radians();
+            let (a, b) = (
+                (start.cos(), start.sin()),
+                ((start + angle).cos(), (start + angle).sin()),
+            );
+            let r = u.sqrt();
+            let (s, t) = (r * (1.0 - v), r * v);
+            (s * a.0 + t * b.0, s * a.1 + t * b.1)
+        };
+        (x * self.aperture, y * self.aperture)
+    }
+
+    fn ray_inner(
+        &self,
+        coord: (f64, f64),
+        offset: (f64, f64),
+        image_size: (usize, usize),
+    ) -> Option<Ray> {
         let (x, y) = coord;
         let (x_offset, y_offset) = offset;
         let (width, height) = image_size;
@@ -114,29 +315,128 @@ impl Camera {
         let x = (x + x_offset) / width as f64;
         let y = (y + y_offset) / height as f64;
 
-        let x = (2.0 * x - 1.0) * self.width_fov;
-        let y = (1.0 - 2.0 * y) * self.height_fov;
+        let x = 2.0 * x - 1.0;
+        let y = 1.0 - 2.0 * y;
 
-        let direction = self.direction * self.z_min + self.right * x + self.up * y;
-        Ray::new(self.position, direction.normalize(), 0)
+        let (origin, direction) = self.project((x, y), height as f64 / width as f64)?;
+        Some(Ray::new(origin, direction.normalize(), 0))
     }
 
-    pub fn ray(&self, coord: (f64, f64), image_size: (usize, usize)) -> Ray {
+    /// Origin and direction of the ray through `(x, y)`, going from -1 to 1 across the image with
+    /// `y` upwards. `aspect` is the height over the width of the image. Fisheyes only cover the
+    /// circle inscribed in the width of the image, there is no ray outside of it.
+    fn project(&self, (x, y): (f64, f64), aspect: f64) -> Option<(Point, Vector)> {
+        let (forward, right, up) = (self.direction, self.right, self.up);
+        let toward = |theta: f64, phi: f64| {
+            forward * theta.cos() + (right * phi.cos() + up * phi.sin()) * theta.sin()
+        };
+
+        Some(match self.projection {
+            Projection::Perspective => {
+                let width_fov = if self.auto_aspect {
+                    self.height_fov / aspect
+                } else {
+                    self.width_fov
+                };
+                let direction = self.direction * self.z_min
+                    + self.right * (x * width_fov)
+                    + self.up * (y * self.height_fov);
+                (self.position, direction)
+            }
+            Projection::Orthographic { width } => {
+                let half = width / 2.0;
+                let origin = self.position + right * (x * half) + up * (y * aspect * half);
+                (origin, forward)
+            }
+            Projection::FisheyeEquidistant { fov } => {
+                let y = y * aspect;
+                if x.hypot(y) > 1.0 {
+                    return None;
+                }
+                let theta = x.hypot(y) * fov.to_radians() / 2.0;
+                (self.position, toward(theta, y.atan2(x)))
+            }
+            Projection::FisheyeEquisolid { fov } => {
+                let y = y * aspect;
+                if x.hypot(y) > 1.0 {
+                    return None;
+                }
+                let scale = (fov.to_radians() / 4.0).sin();
+                let theta = 2.0 * (x.hypot(y) * scale).min(1.0).asin();
+                (self.position, toward(theta, y.atan2(x)))
+            }
+            Projection::Cylindrical { fov } => {
+                // Unit cylinder, the height matches the arc length so that pixels stay square
+                let half = fov.to_radians() / 2.0;
+                let phi = x * half;
+                let direction = forward * phi.cos() + right * phi.sin() + up * (y * aspect * half);
+                (self.position, direction)
+            }
+            Projection::Equirectangular => {
+                let (longitude, latitude) = (x * PI, y * FRAC_PI_2);
+                let direction = (forward * longitude.cos() + right * longitude.sin())
+                    * latitude.cos()
+                    + up * latitude.sin();
+                (self.position, direction)
+            }
+        })
+    }
+
+    /// Moves the origin of `ray` to `lens` on the aperture, keeping the point where it crosses the
+    /// focus plane. Only meaningful for perspective rays.
+    fn through_lens(&self, ray: Ray, lens: (f64, f64)) -> Ray {
+        if self.aperture <= 0.0 {
+            return ray;
+        }
+        let focus = ray.point_at(self.focus_distance / ray.direction.dot(&self.direction));
+        let origin = self.position + self.right * lens.0 + self.up * lens.1;
+        Ray::new(origin, focus - origin, 0)
+    }
+
+    /// Ray through the center of the pixel at `coord`, `None` outside of the image circle of
+    /// fisheyes.
+    pub fn ray(&self, coord: (f64, f64), image_size: (usize, usize)) -> Option<Ray> {
         self.ray_inner(coord, (0.5, 0.5), image_size)
     }
 
+    /// Ray through `position` in image coordinates, where pixel `(x, y)` covers
+    /// `[x, x + 1) × [y, y + 1)`. The lens position is the next dimension of `sampler`.
+    pub fn sample_ray(
+        &self,
+        position: (f64, f64),
+        image_size: (usize, usize),
+        sampler: &mut impl PixelSampler,
+    ) -> Option<Ray> {
+        let ray = self.ray_inner(position, (0.0, 0.0), image_size)?;
+        Some(
+            if self.aperture > 0.0 && self.projection == Projection::Perspective {
+                let (u, v) = sampler.get_2d();
+                self.through_lens(ray, self.lens_point(u, v))
+            } else {
+                ray
+            },
+        )
+    }
+
+    /// Rays of the first `aa_samples` samples of the pixel at `coord`, jittered inside the pixel.
+    /// Samples outside of the image circle of fisheyes are skipped.
     pub fn ray_aa(
         &self,
         coord: (f64, f64),
         image_size: (usize, usize),
         aa_samples: usize,
-        rng: &mut impl Rng,
+        sampler: &mut impl PixelSampler,
     ) -> Vec<Ray> {
-        (0..aa_samples)
-            .map(|_| {
-                let x_offset = rng.gen_range(0.0..1.0);
-                let y_offset = rng.gen_range(0.0..1.0);
-                self.ray_inner(coord, (x_offset, y_offset), image_size)
+        let pixel = (coord.0 as u32, coord.1 as u32);
+        (0..aa_samples as u32)
+            .filter_map(|index| {
+                sampler.start_pixel_sample(pixel, index, aa_samples as u32);
+                let (x_offset, y_offset) = sampler.get_2d();
+                self.sample_ray(
+                    (coord.0 + x_offset, coord.1 + y_offset),
+                    image_size,
+                    sampler,
+                )
             })
             .collect::<Vec<_>>()
     }
@@ -148,7 +448,7 @@ impl Camera {
         let (width, height) = image_size;
         (0..height)
             .flat_map(move |y| (0..width).map(move |x| (x, y)))
-            .map(move |(x, y)| (x, y, self.ray((x as f64, y as f64), image_size)))
+            .filter_map(move |(x, y)| Some((x, y, self.ray((x as f64, y as f64), image_size)?)))
     }
 
     pub fn ray_par_iter(
@@ -159,7 +459,7 @@ impl Camera {
         (0..height)
             .into_par_iter()
             .flat_map(move |y| (0..width).into_par_iter().map(move |x| (x, y)))
-            .map(move |(x, y)| (x, y, self.ray((x as f64, y as f64), image_size)))
+            .filter_map(move |(x, y)| Some((x, y, self.ray((x as f64, y as f64), image_size)?)))
     }
 }
 
@@ -173,11 +473,23 @@ impl Default for Camera {
             direction: Vector::new(1.0, 0.0, 0.0),
             up: Vector::new(0.0, 1.0, 0.0),
             right: Vector::new(0.0, 0.0, 1.0),
+            world_up: Vector::new(0.0, 1.0, 0.0),
+            roll: 0.0,
             width_fov,
             height_fov,
+            auto_aspect: true,
             z_min: 1.0,
             max_bounces: 5,
             anti_aliasing: 1,
+            filter: Filter::default(),
+            adaptive: None,
+            integrator: Integrator::default(),
+            sampler: Sampler::default(),
+            projection: Projection::default(),
+            aperture: 0.0,
+            focus_distance: 10.0,
+            blades: 0,
+            blade_rotation: 0.0,
         }
     }
 }
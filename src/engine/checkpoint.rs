@@ -0,0 +1,315 @@
+use std::{
+    fmt::{self, Write as _},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::utils::color::Color;
+
+use super::{
+    film::{FilmTile, PixelStatistics},
+    sampler::mix,
+    scene::Scene,
+};
+
+/// Identifies the file format, the last two bytes are the version.
+const MAGIC: &[u8; 8] = b"RTCKPT01";
+
+/// Bytes before the pixels: the magic, the scene hash, the size, the samples and the pass.
+const HEADER_SIZE: u64 = 32;
+
+/// Bytes per pixel: the weighted color sum, the weight and the statistics.
+const PIXEL_SIZE: u64 = 52;
+
+/// Accumulated samples of a render, saved to resume it later.
+///
+/// Every pixel keeps its sums and its own sample count, so a render stopped in the middle of a
+/// pass resumes exactly where it was. The sample count of the scene can be raised to add samples
+/// to a finished render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Hash of everything but the sample counts, see `scene_hash`.
+    pub scene_hash: u64,
+    /// Samples per pixel the last pass was aiming for.
+    pub samples: u32,
+    /// Index of the next pass.
+    pub pass: u32,
+    /// Filtered sums covering the whole image.
+    pub accumulated: FilmTile,
+    pub statistics: Vec<PixelStatistics>,
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// The file is not a checkpoint or is truncated.
+    Format(String),
+    /// The checkpoint was rendered from another scene or at another size.
+    SceneMismatch,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(source) => write!(f, "{}", source),
+            CheckpointError::Format(message) => write!(f, "invalid checkpoint: {}", message),
+            CheckpointError::SceneMismatch => write!(
+                f,
+                "the checkpoint was rendered from a different scene or image size"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CheckpointError::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(error: io::Error) -> CheckpointError {
+        CheckpointError::Io(error)
+    }
+}
+
+impl Checkpoint {
+    /// Empty render of `scene` at the given size.
+    pub fn new(scene: &Scene, width: u32, height: u32) -> Checkpoint {
+        Checkpoint {
+            scene_hash: scene_hash(scene, width, height),
+            samples: 0,
+            pass: 0,
+            accumulated: FilmTile::new(0, 0, width, height),
+            statistics: vec![PixelStatistics::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.accumulated.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.accumulated.height
+    }
+
+    /// Whether the checkpoint can be resumed to render `scene` at its size.
+    pub fn matches(&self, scene: &Scene) -> bool {
+        self.scene_hash == scene_hash(scene, self.width(), self.height())
+    }
+
+    /// Writes the checkpoint next to `path` before moving it in place, so that a crash while
+    /// saving keeps the previous checkpoint.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        let mut file = BufWriter::new(File::create(&temporary)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&self.scene_hash.to_le_bytes())?;
+        for value in [self.width(), self.height(), self.samples, self.pass] {
+            file.write_all(&value.to_le_bytes())?;
+        }
+        let pixels = self
+            .accumulated
+            .sums
+            .iter()
+            .zip(&self.accumulated.weights)
+            .zip(&self.statistics);
+        for ((sum, weight), pixel) in pixels {
+            for value in [sum.r, sum.g, sum.b, *weight] {
+                file.write_all(&value.to_le_bytes())?;
+            }
+            file.write_all(&pixel.count.to_le_bytes())?;
+            file.write_all(&pixel.mean.to_le_bytes())?;
+            file.write_all(&pixel.m2.to_le_bytes())?;
+        }
+        file.into_inner()?.sync_all()?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Checkpoint, CheckpointError> {
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        let mut file = BufReader::new(file);
+        let mut magic = [0; 8];
+        read(&mut file, &mut magic)?;
+        if &magic != MAGIC {
+            return Err(CheckpointError::Format("unknown file format".to_string()));
+        }
+
+        let scene_hash = u64::from_le_bytes(read_bytes(&mut file)?);
+        let width = u32::from_le_bytes(read_bytes(&mut file)?);
+        let height = u32::from_le_bytes(read_bytes(&mut file)?);
+        let samples = u32::from_le_bytes(read_bytes(&mut file)?);
+        let pass = u32::from_le_bytes(read_bytes(&mut file)?);
+
+        // Checked before allocating anything, a corrupt size could ask for terabytes
+        let count = (width as u64)
+            .checked_mul(height as u64)
+            .filter(|count| {
+                count
+                    .checked_mul(PIXEL_SIZE)
+                    .and_then(|size| size.checked_add(HEADER_SIZE))
+                    == Some(length)
+            })
+            .ok_or_else(|| {
+                CheckpointError::Format("file length doesn't match the image size".to_string())
+            })? as usize;
+
+        let mut accumulated = FilmTile::new(0, 0, width, height);
+        let mut statistics = Vec::with_capacity(count);
+        for index in 0..count {
+            let mut next = || read_bytes(&mut file).map(f64::from_le_bytes);
+            accumulated.sums[index] = Color::new(next()?, next()?, next()?);
+            accumulated.weights[index] = next()?;
+            statistics.push(PixelStatistics {
+                count: u32::from_le_bytes(read_bytes(&mut file)?),
+                mean: f64::from_le_bytes(read_bytes(&mut file)?),
+                m2: f64::from_le_bytes(read_bytes(&mut file)?),
+            });
+        }
+
+        Ok(Checkpoint {
+            scene_hash,
+            samples,
+            pass,
+            accumulated,
+            statistics,
+        })
+    }
+}
+
+fn read(reader: &mut impl Read, buffer: &mut [u8]) -> Result<(), CheckpointError> {
+    reader
+        .read_exact(buffer)
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                CheckpointError::Format("file is truncated".to_string())
+            }
+            _ => CheckpointError::Io(error),
+        })
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], CheckpointError> {
+    let mut buffer = [0; N];
+    read(reader, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Hash of everything a sample depends on: the image size, the camera, the objects, the lights
+/// and the background. Sample counts and adaptive sampling are left out so that a render can be
+/// resumed with more samples. Pictures are identified by their `checksum`.
+pub fn scene_hash(scene: &Scene, width: u32, height: u32) -> u64 {
+    let mut camera = scene.camera.clone();
+    camera.anti_aliasing = 0;
+    camera.adaptive = None;
+
+    let mut hasher = Hasher::default();
+    // Writing to the hasher cannot fail
+    let _ = write!(
+        hasher,
+        "{}x{} {:?} {:?} {:?} {:?}",
+        width, height, camera, scene.objects, scene.lights, scene.background
+    );
+    hasher.0
+}
+
+/// 64-bit FNV-1a hash of the text written to it, stable across Rust releases unlike
+/// `DefaultHasher`.
+pub struct Hasher(pub u64);
+
+impl Default for Hasher {
+    fn default() -> Hasher {
+        Hasher(0xcbf29ce484222325)
+    }
+}
+
+impl fmt::Write for Hasher {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        for byte in text.bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+        Ok(())
+    }
+}
+
+/// Hash of the texels of a picture, computed once when it is loaded so that `scene_hash` notices
+/// edited environment maps and textures.
+pub fn checksum(texels: &[Color]) -> u64 {
+    texels.iter().fold(0x9e3779b97f4a7c15, |hash, texel| {
+        [texel.r, texel.g, texel.b]
+            .iter()
+            .fold(hash, |hash, value| mix(hash ^ value.to_bits()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn checkpoint() -> Checkpoint {
+        let mut checkpoint = Checkpoint::new(&Scene::default(), 3, 2);
+        checkpoint.samples = 8;
+        checkpoint.pass = 2;
+        for index in 0..6 {
+            let value = index as f64;
+            checkpoint.accumulated.sums[index] = Color::new(value, value * 0.5, -value);
+            checkpoint.accumulated.weights[index] = 1.0 + value;
+            checkpoint.statistics[index] = PixelStatistics {
+                count: index as u32,
+                mean: value * 0.25,
+                m2: value * value,
+            };
+        }
+        checkpoint
+    }
+
+    fn temporary(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rusttracer-{}-{}.ck", std::process::id(), name))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = temporary("round-trip");
+        let checkpoint = checkpoint();
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert!(loaded.matches(&Scene::default()));
+    }
+
+    #[test]
+    fn files_of_the_wrong_length_are_rejected() {
+        let path = temporary("length");
+        checkpoint().save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        let mut oversized = bytes.clone();
+        oversized.extend_from_slice(&[0; 4]);
+        // A corrupt size must not be allocated before the length is checked
+        let mut huge = bytes.clone();
+        huge[16..24].copy_from_slice(&[0xff; 8]);
+        for corrupt in [&bytes[..bytes.len() - 1], &oversized, &huge] {
+            fs::write(&path, corrupt).unwrap();
+            let result = Checkpoint::load(&path);
+            assert!(
+                matches!(result, Err(CheckpointError::Format(_))),
+                "{:?}",
+                result
+            );
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
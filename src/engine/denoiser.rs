@@ -0,0 +1,286 @@
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::utils::color::Color;
+
+use super::{aov::Aov, film::Film};
+
+/// Weights of the B3 spline kernel, applied in both directions.
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Edge-avoiding À-trous wavelet filter (Dammertz et al.), removing the noise of low sample renders.
+///
+/// Every iteration blurs the image with a 5×5 kernel whose taps are twice as far apart as in the
+/// previous one, so a few iterations cover a wide footprint cheaply. Taps are weighted down when
+/// their color, or the albedo, normal and depth AOVs of the film, differ from the pixel's, which
+/// keeps edges sharp. The color is divided by the albedo while filtering so that textures are
+/// preserved. Without AOVs only the colors guide the filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    /// Blend between the render (0) and the filtered image (1).
+    pub strength: f64,
+    /// Number of iterations, the footprint of the filter is about `4 × 2^iterations` pixels. Taps
+    /// falling outside of the image are skipped, so more than about 10 iterations only cost time.
+    pub iterations: u32,
+    /// Tolerance to color differences, halved at each iteration. Higher values blur more.
+    pub color_sigma: f64,
+    pub albedo_sigma: f64,
+    pub normal_sigma: f64,
+    /// Tolerance to depth differences, relative to the change expected from the depth gradient.
+    pub depth_sigma: f64,
+}
+
+impl Denoiser {
+    pub fn new(strength: f64) -> Denoiser {
+        Denoiser {
+            strength,
+            ..Denoiser::default()
+        }
+    }
+
+    pub fn with_strength(&self, strength: f64) -> Denoiser {
+        Denoiser { strength, ..*self }
+    }
+
+    pub fn with_iterations(&self, iterations: u32) -> Denoiser {
+        Denoiser {
+            iterations,
+            ..*self
+        }
+    }
+
+    pub fn with_color_sigma(&self, color_sigma: f64) -> Denoiser {
+        Denoiser {
+            color_sigma,
+            ..*self
+        }
+    }
+
+    /// Returns a copy of `film` whose pixels are denoised, its AOVs are used as guides.
+    pub fn denoise(&self, film: &Film) -> Film {
+        let guides = Guides::new(film);
+        let size = film.pixels.len();
+
+        // Filter the irradiance rather than the color so that textures don't get blurred
+        let albedo = |index: usize| {
+            guides.albedo.map_or(Color::new(1.0, 1.0, 1.0), |albedo| {
+                let albedo = albedo[index];
+                let channel = |value: f64| if value > 1e-3 { value } else { 1.0 };
+                Color::new(channel(albedo.r), channel(albedo.g), channel(albedo.b))
+            })
+        };
+        let mut image = (0..size)
+            .map(|index| {
+                let (pixel, albedo) = (film.pixels[index], albedo(index));
+                Color::new(pixel.r / albedo.r, pixel.g / albedo.g, pixel.b / albedo.b)
+            })
+            .collect::<Vec<_>>();
+
+        for iteration in 0..self.iterations {
+            image = self.iterate(film.width, film.height, &image, &guides, iteration);
+        }
+
+        let mut denoised = film.clone();
+        for (index, pixel) in denoised.pixels.iter_mut().enumerate() {
+            let filtered = image[index] * albedo(index);
+            *pixel = *pixel * (1.0 - self.strength) + filtered * self.strength;
+        }
+        denoised
+    }
+
+    fn iterate(
+        &self,
+        width: u32,
+        height: u32,
+        image: &[Color],
+        guides: &Guides,
+        iteration: u32,
+    ) -> Vec<Color> {
+        // Past 32 iterations the taps are further apart than any image is wide, which keeps the
+        // offsets below from overflowing
+        let iteration = iteration.min(32);
+        let step = 1i64 << iteration;
+        let color_sigma = self.color_sigma * 0.5f64.powi(iteration as i32);
+        // Colors are compared after compressing their range, bright outliers would stop the
+        // filter everywhere otherwise
+        let compress = |color: Color| {
+            Color::new(
+                color.r / (1.0 + color.r.abs()),
+                color.g / (1.0 + color.g.abs()),
+                color.b / (1.0 + color.b.abs()),
+            )
+        };
+        let distance = |a: Color, b: Color| {
+            let difference = a - b;
+            difference.r * difference.r + difference.g * difference.g + difference.b * difference.b
+        };
+
+        (0..width as i64 * height as i64)
+            .into_par_iter()
+            .map(|index| {
+                let (x, y) = (index % width as i64, index / width as i64);
+                let index = index as usize;
+                let color = compress(image[index]);
+
+                let mut sum = Color::zero();
+                let mut total = 0.0;
+                for (j, y_weight) in KERNEL.iter().enumerate() {
+                    let other_y = y + (j as i64 - 2) * step;
+                    if other_y < 0 || other_y >= height as i64 {
+                        continue;
+                    }
+                    for (i, x_weight) in KERNEL.iter().enumerate() {
+                        let other_x = x + (i as i64 - 2) * step;
+                        if other_x < 0 || other_x >= width as i64 {
+                            continue;
+                        }
+                        let other = (other_y * width as i64 + other_x) as usize;
+
+                        let mut exponent =
+                            distance(color, compress(image[other])) / (color_sigma * color_sigma);
+                        if let Some(albedo) = guides.albedo {
+                            exponent += distance(albedo[index], albedo[other])
+                                / (self.albedo_sigma * self.albedo_sigma);
+                        }
+                        if let Some(normal) = guides.normal {
+                            exponent += distance(normal[index], normal[other])
+                                / (self.normal_sigma * self.normal_sigma);
+                        }
+                        if let Some(depth) = guides.depth {
+                            let (depth, other_depth) = (depth[index].r, depth[other].r);
+                            let gradient = guides.gradients[index];
+                            let expected = (gradient.0 * (other_x - x) as f64
+                                + gradient.1 * (other_y - y) as f64)
+                                .abs();
+                            exponent += (depth - other_depth).abs()
+                                / (self.depth_sigma * expected + 1e-2 * depth.max(1e-3));
+                        }
+
+                        let weight = x_weight * y_weight * (-exponent).exp();
+                        sum = sum + image[other] * weight;
+                        total += weight;
+                    }
+                }
+                // The center tap always has a weight of at least 9/64
+                sum / total
+            })
+            .collect()
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Denoiser {
+        Denoiser {
+            strength: 1.0,
+            iterations: 5,
+            color_sigma: 0.5,
+            albedo_sigma: 0.1,
+            normal_sigma: 0.3,
+            depth_sigma: 1.0,
+        }
+    }
+}
+
+/// AOVs of a film guiding the denoiser.
+struct Guides<'a> {
+    albedo: Option<&'a [Color]>,
+    /// Shading normals when available.
+    normal: Option<&'a [Color]>,
+    depth: Option<&'a [Color]>,
+    /// Change of depth per pixel in x and y, by central differences.
+    gradients: Vec<(f64, f64)>,
+}
+
+impl<'a> Guides<'a> {
+    fn new(film: &'a Film) -> Guides<'a> {
+        let aov = |aov: Aov| film.aovs.get(&aov).map(|film| film.pixels.as_slice());
+        let depth = aov(Aov::Depth);
+        let (width, height) = (film.width as usize, film.height as usize);
+        let gradients = depth.map_or_else(Vec::new, |depth| {
+            let at = |x: usize, y: usize| depth[y * width + x].r;
+            (0..width * height)
+                .map(|index| {
+                    let (x, y) = (index % width, index / width);
+                    let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+                    let (top, bottom) = (y.saturating_sub(1), (y + 1).min(height - 1));
+                    (
+                        (at(right, y) - at(left, y)) / (right - left).max(1) as f64,
+                        (at(x, bottom) - at(x, top)) / (bottom - top).max(1) as f64,
+                    )
+                })
+                .collect()
+        });
+
+        Guides {
+            albedo: aov(Aov::Albedo),
+            normal: aov(Aov::ShadingNormal).or(aov(Aov::Normal)),
+            depth,
+            gradients,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Film whose pixels all differ, with every AOV guiding the denoiser.
+    fn film(width: u32, height: u32) -> Film {
+        let mut film = Film::new(width, height);
+        for (index, pixel) in film.pixels.iter_mut().enumerate() {
+            *pixel = Color::new(index as f64, 1.0, 0.5 * index as f64);
+        }
+        for aov in [Aov::Albedo, Aov::Normal, Aov::Depth] {
+            let mut guide = Film::new(width, height);
+            for (index, pixel) in guide.pixels.iter_mut().enumerate() {
+                *pixel = Color::new(0.5, 0.5, 1.0) * (1.0 + index as f64);
+            }
+            film.aovs.insert(aov, guide);
+        }
+        film
+    }
+
+    fn is_finite(film: &Film) -> bool {
+        film.pixels
+            .iter()
+            .all(|pixel| pixel.r.is_finite() && pixel.g.is_finite() && pixel.b.is_finite())
+    }
+
+    #[test]
+    fn single_pixel_is_unchanged() {
+        let film = film(1, 1);
+        let mut plain = film.clone();
+        plain.aovs.clear();
+        for film in [film, plain] {
+            assert_eq!(Denoiser::default().denoise(&film).pixels, film.pixels);
+        }
+    }
+
+    #[test]
+    fn small_films_stay_finite() {
+        let film = film(2, 2);
+        let denoised = Denoiser::default().with_iterations(40).denoise(&film);
+        assert!(is_finite(&denoised));
+
+        let mut uniform = Film::new(2, 2);
+        uniform.pixels = vec![Color::new(0.25, 0.5, 1.0); 4];
+        let denoised = Denoiser::default().denoise(&uniform);
+        for pixel in denoised.pixels {
+            let difference = pixel - Color::new(0.25, 0.5, 1.0);
+            assert!([difference.r, difference.g, difference.b]
+                .iter()
+                .all(|channel| channel.abs() < 1e-12));
+        }
+    }
+
+    #[test]
+    fn huge_iterations_do_not_overflow() {
+        let film = film(2, 2);
+        let guides = Guides::new(&film);
+        let denoiser = Denoiser::default();
+        for iteration in [31, 32, 63, 64, u32::MAX] {
+            let image = denoiser.iterate(2, 2, &film.pixels, &guides, iteration);
+            // Every tap but the pixel itself falls outside of the image
+            assert_eq!(image, film.pixels);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use image::{
+    codecs::hdr::HdrEncoder,
+    error::{EncodingError, ImageError, ImageFormatHint},
+    ImageBuffer, ImageFormat, Rgb, RgbImage,
+};
+
+use crate::utils::color::Color;
+
+use super::{aov::Aov, filter::Filter, tone_mapping::ToneMapper};
+
+/// Framebuffer of unclamped linear colors, the result of a render.
+///
+/// Floating point formats keep the full dynamic range, conversion to 8-bit only happens in
+/// `to_rgb_image` or when saving to a low dynamic range format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    /// Row by row from the top left corner.
+    pub pixels: Vec<Color>,
+    /// Number of samples taken in each pixel.
+    pub samples: Vec<u32>,
+    /// Extra images rendered along this one, of the same size.
+    pub aovs: BTreeMap<Aov, Film>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Film {
+        Film {
+            width,
+            height,
+            pixels: vec![Color::zero(); (width * height) as usize],
+            samples: vec![0; (width * height) as usize],
+            aovs: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.pixels[index] = color;
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "pixel ({}, {}) out of bounds",
+            x,
+            y
+        );
+        (y * self.width + x) as usize
+    }
+
+    /// Tone maps, clamps and gamma corrects every pixel.
+    pub fn to_rgb_image(&self, tone_mapper: &ToneMapper) -> RgbImage {
+        let colors = tone_mapper.apply(self);
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            colors[(y * self.width + x) as usize].into()
+        })
+    }
+
+    /// Sample counts on a logarithmic scale from blue for the fewest samples to red for the most,
+    /// showing where adaptive sampling spent its time.
+    pub fn sample_heatmap(&self) -> RgbImage {
+        const RAMP: [(f64, f64, f64); 5] = [
+            (0.05, 0.05, 0.4),
+            (0.0, 0.6, 0.9),
+            (0.2, 0.8, 0.2),
+            (1.0, 0.85, 0.0),
+            (0.9, 0.1, 0.05),
+        ];
+        let min = self.samples.iter().copied().min().unwrap_or(0).max(1) as f64;
+        let max = self.samples.iter().copied().max().unwrap_or(0).max(1) as f64;
+
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let count = self.samples[self.index(x, y)].max(1) as f64;
+            let t = if max > min {
+                (count / min).ln() / (max / min).ln()
+            } else {
+                0.0
+            };
+            let position = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as f64;
+            let index = (position as usize).min(RAMP.len() - 2);
+            let blend = position - index as f64;
+            let (from, to) = (RAMP[index], RAMP[index + 1]);
+            Rgb([
+                ((from.0 + (to.0 - from.0) * blend) * 255.0).round() as u8,
+                ((from.1 + (to.1 - from.1) * blend) * 255.0).round() as u8,
+                ((from.2 + (to.2 - from.2) * blend) * 255.0).round() as u8,
+            ])
+        })
+    }
+
+    fn to_rgb32f(&self) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+        ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            let color = self.get_pixel(x, y);
+            Rgb([color.r as f32, color.g as f32, color.b as f32])
+        })
+    }
+
+    /// Saves the film, the format is deduced from the extension: `exr`, `hdr` and `pfm` files keep
+    /// the linear values, other formats go through `to_rgb_image`.
+    ///
+    /// AOVs are layers of EXR files, with other formats they are saved next to the image as
+    /// `name.<aov>.ext`. 8-bit AOVs are previews, see `Aov::to_rgb_image`.
+    pub fn save(&self, path: impl AsRef<Path>, tone_mapper: &ToneMapper) -> Result<(), ImageError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("exr") => return self.write_exr(path),
+            Some("hdr") => self.write_hdr(path)?,
+            Some("pfm") => self.write_pfm(path)?,
+            _ => self.to_rgb_image(tone_mapper).save(path)?,
+        }
+
+        for (aov, film) in &self.aovs {
+            let path = aov_path(path, *aov);
+            match extension.as_deref() {
+                Some("hdr") => film.write_hdr(path)?,
+                Some("pfm") => film.write_pfm(path)?,
+                _ => aov.to_rgb_image(film, tone_mapper).save(path)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a 32-bit float OpenEXR file, AOVs are stored as `<aov>.<channel>` channels.
+    pub fn write_exr(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        if self.aovs.is_empty() {
+            return self
+                .to_rgb32f()
+                .save_with_format(path, ImageFormat::OpenExr);
+        }
+
+        use exr::prelude::*;
+        let channels = |film: &Film, names: &[&str], prefix: &str| {
+            names
+                .iter()
+                .enumerate()
+                .map(|(index, name)| {
+                    let samples = film
+                        .pixels
+                        .iter()
+                        .map(|color| [color.r, color.g, color.b][index] as f32)
+                        .collect();
+                    AnyChannel::new(
+                        Text::from(format!("{}{}", prefix, name).as_str()),
+                        FlatSamples::F32(samples),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut list = channels(self, &["R", "G", "B"], "");
+        for (aov, film) in &self.aovs {
+            list.extend(channels(film, aov.channels(), &format!("{}.", aov.name())));
+        }
+
+        let layer = Layer::new(
+            (self.width as usize, self.height as usize),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(list)),
+        );
+        Image::from_layer(layer)
+            .write()
+            .to_file(path)
+            .map_err(|error| {
+                ImageError::Encoding(EncodingError::new(
+                    ImageFormatHint::Exact(ImageFormat::OpenExr),
+                    error,
+                ))
+            })
+    }
+
+    /// Writes a Radiance RGBE file.
+    pub fn write_hdr(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let file = BufWriter::new(File::create(path)?);
+        let pixels = self.to_rgb32f().pixels().copied().collect::<Vec<_>>();
+        HdrEncoder::new(file).encode(&pixels, self.width as usize, self.height as usize)
+    }
+
+    /// Writes a little endian color Portable Float Map, whose rows go from bottom to top.
+    pub fn write_pfm(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(file, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let color = self.get_pixel(x, y);
+                for value in [color.r, color.g, color.b] {
+                    file.write_all(&(value as f32).to_le_bytes())?;
+                }
+            }
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Path of the image of `aov` saved next to `path`.
+fn aov_path(path: &Path, aov: Aov) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}.{}", stem, aov.name());
+    if let Some(extension) = path.extension() {
+        name = format!("{}.{}", name, extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// Filtered sums of the samples landing around a rectangle of the image.
+///
+/// Samples are splatted on every pixel of the tile their filter reaches, tiles of neighbouring
+/// regions overlap and are merged into one covering the whole image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilmTile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Weighted sums of the samples, row by row.
+    pub sums: Vec<Color>,
+    pub weights: Vec<f64>,
+}
+
+impl FilmTile {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> FilmTile {
+        let size = (width * height) as usize;
+        FilmTile {
+            x,
+            y,
+            width,
+            height,
+            sums: vec![Color::zero(); size],
+            weights: vec![0.0; size],
+        }
+    }
+
+    /// Adds a sample taken at `position` in image coordinates, where pixel `(x, y)` covers
+    /// `[x, x + 1) × [y, y + 1)`.
+    pub fn add_sample(&mut self, position: (f64, f64), color: Color, filter: &Filter) {
+        let radius = filter.radius();
+        let range = |position: f64, start: u32, size: u32| {
+            let first = (position - 0.5 - radius).ceil().max(start as f64) as u32;
+            let last = (position - 0.5 + radius)
+                .floor()
+                .min((start + size) as f64 - 1.0);
+            (first, last)
+        };
+        let (x_first, x_last) = range(position.0, self.x, self.width);
+        let (y_first, y_last) = range(position.1, self.y, self.height);
+        if x_last < x_first as f64 || y_last < y_first as f64 {
+            return;
+        }
+
+        for y in y_first..=y_last as u32 {
+            for x in x_first..=x_last as u32 {
+                let weight =
+                    filter.evaluate(position.0 - x as f64 - 0.5, position.1 - y as f64 - 0.5);
+                if weight != 0.0 {
+                    let index = ((y - self.y) * self.width + x - self.x) as usize;
+                    self.sums[index] = self.sums[index] + color * weight;
+                    self.weights[index] += weight;
+                }
+            }
+        }
+    }
+
+    /// Adds the sums of `other` where the tiles overlap.
+    pub fn merge(&mut self, other: &FilmTile) {
+        let (x_start, x_end) = (
+            self.x.max(other.x),
+            (self.x + self.width).min(other.x + other.width),
+        );
+        let (y_start, y_end) = (
+            self.y.max(other.y),
+            (self.y + self.height).min(other.y + other.height),
+        );
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+                let index = ((y - self.y) * self.width + x - self.x) as usize;
+                let other_index = ((y - other.y) * other.width + x - other.x) as usize;
+                self.sums[index] = self.sums[index] + other.sums[other_index];
+                self.weights[index] += other.weights[other_index];
+            }
+        }
+    }
+
+    /// Normalized color of the pixel at `(x, y)` in image coordinates, black without samples.
+    pub fn color(&self, x: u32, y: u32) -> Color {
+        let index = ((y - self.y) * self.width + x - self.x) as usize;
+        let weight = self.weights[index];
+        if weight.abs() > 1e-12 {
+            self.sums[index] / weight
+        } else {
+            Color::zero()
+        }
+    }
+
+    /// Writes the normalized pixels of the tile into `film`.
+    pub fn develop(&self, film: &mut Film) {
+        for y in self.y..(self.y + self.height).min(film.height) {
+            for x in self.x..(self.x + self.width).min(film.width) {
+                film.put_pixel(x, y, self.color(x, y));
+            }
+        }
+    }
+}
+
+/// Running mean and variance of the luminance of the samples of a pixel, using Welford's method.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PixelStatistics {
+    pub count: u32,
+    pub mean: f64,
+    /// Sum of the squared differences to the mean.
+    pub m2: f64,
+}
+
+impl PixelStatistics {
+    pub fn add(&mut self, luminance: f64) {
+        self.count += 1;
+        let delta = luminance - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (luminance - self.mean);
+    }
+
+    /// Unbiased variance of the samples.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.m2 / (self.count - 1) as f64
+    }
+
+    /// Standard error of the mean relative to the mean, infinite below two samples. Dark pixels
+    /// are compared to a floor so that they do not need countless samples.
+    pub fn relative_error(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        (self.variance() / self.count as f64).sqrt() / self.mean.abs().max(0.01)
+    }
+}
@@ -0,0 +1,132 @@
+use std::f64::consts::PI;
+
+/// Reconstruction filter weighting the samples around each pixel center.
+///
+/// Radii are in pixels. Samples are splatted on every pixel whose center is within the radius,
+/// wider filters trade sharpness for smoother edges and less aliasing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Equal weights inside the radius, 0.5 averages the samples of each pixel.
+    Box { radius: f64 },
+    /// Weights decreasing linearly to 0 at the radius.
+    Tent { radius: f64 },
+    /// Gaussian of standard deviation `sigma`, shifted to reach 0 at the radius.
+    Gaussian { radius: f64, sigma: f64 },
+    /// Mitchell-Netravali cubic, `b = c = 1/3` is the recommended compromise between blurring and
+    /// ringing.
+    Mitchell { radius: f64, b: f64, c: f64 },
+    /// Sinc windowed by a wider sinc, with as many lobes as the radius. The sharpest filter, with
+    /// some ringing on strong edges.
+    Lanczos { radius: f64 },
+}
+
+impl Filter {
+    pub fn new_box() -> Filter {
+        Filter::Box { radius: 0.5 }
+    }
+
+    pub fn new_tent() -> Filter {
+        Filter::Tent { radius: 1.0 }
+    }
+
+    pub fn new_gaussian() -> Filter {
+        Filter::Gaussian {
+            radius: 1.5,
+            sigma: 0.5,
+        }
+    }
+
+    pub fn new_mitchell() -> Filter {
+        Filter::Mitchell {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        }
+    }
+
+    pub fn new_lanczos() -> Filter {
+        Filter::Lanczos { radius: 3.0 }
+    }
+
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius } => radius,
+        }
+    }
+
+    pub fn with_radius(&self, radius: f64) -> Filter {
+        match *self {
+            Filter::Box { .. } => Filter::Box { radius },
+            Filter::Tent { .. } => Filter::Tent { radius },
+            Filter::Gaussian { sigma, .. } => Filter::Gaussian { radius, sigma },
+            Filter::Mitchell { b, c, .. } => Filter::Mitchell { radius, b, c },
+            Filter::Lanczos { .. } => Filter::Lanczos { radius },
+        }
+    }
+
+    /// Weight of a sample at `(x, y)` pixels from a pixel center. Mitchell and Lanczos have
+    /// negative lobes.
+    pub fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        match *self {
+            // Half open so that a sample on the border of two pixels only counts for one
+            Filter::Box { radius } => {
+                if x >= -radius && x < radius {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Tent { radius } => (1.0 - x.abs() / radius).max(0.0),
+            Filter::Gaussian { radius, sigma } => {
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(radius)).max(0.0)
+            }
+            Filter::Mitchell { radius, b, c } => {
+                let x = 2.0 * x.abs() / radius;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Lanczos { radius } => {
+                if x.abs() < radius {
+                    sinc(x) * sinc(x / radius)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::new_box()
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
@@ -0,0 +1,69 @@
+use crate::utils::color::Color;
+
+use super::{intersection::Intersection, ray::Ray, sampler::PixelSampler, scene::Scene};
+
+/// Light transport algorithm used to compute the color of a camera ray.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Integrator {
+    /// Direct lighting with mirror reflections, see `Ray::cast`.
+    #[default]
+    Whitted,
+    /// Unbiased Monte Carlo path tracing with next-event estimation, see `Ray::trace_path`.
+    PathTracing,
+}
+
+impl Integrator {
+    pub fn radiance(&self, ray: &Ray, scene: &Scene, sampler: &mut impl PixelSampler) -> Color {
+        match self {
+            Integrator::Whitted => ray.cast(scene, sampler),
+            Integrator::PathTracing => ray.trace_path(scene, sampler),
+        }
+    }
+
+    /// Like `radiance` with the light split by its path, see `RadianceSplit`, along with the index
+    /// of the first object hit and its intersection.
+    pub fn radiance_split(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut impl PixelSampler,
+    ) -> (RadianceSplit, Option<(usize, Intersection)>) {
+        match self {
+            Integrator::Whitted => ray.cast_split(scene, sampler),
+            Integrator::PathTracing => ray.trace_path_split(scene, sampler),
+        }
+    }
+
+    /// Whether a single sample per pixel is a noisy estimate that has to be averaged everywhere.
+    pub fn is_stochastic(&self) -> bool {
+        match self {
+            Integrator::Whitted => false,
+            Integrator::PathTracing => true,
+        }
+    }
+}
+
+/// Radiance of a ray split by what happens at the first surface it hits.
+///
+/// `direct` is the light of the lights reflected by the diffuse lobe (with the ambient term and
+/// the background seen directly), `indirect` the light reaching the surface after a diffuse
+/// bounce and `specular` the highlights and everything seen through mirror reflections and
+/// refractions. `total` is their sum, the usual radiance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadianceSplit {
+    pub total: Color,
+    pub direct: Color,
+    pub indirect: Color,
+    pub specular: Color,
+}
+
+impl RadianceSplit {
+    pub fn new(direct: Color, indirect: Color, specular: Color) -> RadianceSplit {
+        RadianceSplit {
+            total: direct + indirect + specular,
+            direct,
+            indirect,
+            specular,
+        }
+    }
+}
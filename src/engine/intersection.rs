@@ -12,20 +12,43 @@ pub struct Intersection {
     pub point: Point,
     pub obj: Object,
     pub material: Material,
+    /// Shading normal, interpolated on smooth triangles.
     pub normal: Vector,
+    /// Normal of the actual surface, the same as `normal` except on smooth triangles.
+    pub geometric_normal: Vector,
+    /// Texture coordinates of the point.
+    pub uv: (f64, f64),
+    /// Index of the texture in `Object::textures` of the object hit, the face's for meshes.
+    pub texture: u32,
 }
 
 impl Intersection {
     pub fn new(distance: f64, ray: &Ray, obj: Object) -> Intersection {
-        let material = obj.material_at(&(ray.origin + ray.direction * distance));
-        let normal = obj.normal(&(ray.origin + ray.direction * distance));
+        let point = ray.origin + ray.direction * distance;
+        let material = obj.material_at(&point);
+        let normal = obj.normal(&point);
+        let geometric_normal = match &obj {
+            Object::SmoothTriangle(triangle) => triangle.face_normal(),
+            _ => normal,
+        };
+        // Meshes and instances hand their primitives over, so only those are ever hit
+        let uv = match &obj {
+            Object::Sphere(sphere) => sphere.uv(&point),
+            Object::Plane(plane) => plane.uv(&point),
+            Object::Triangle(triangle) => triangle.uv(&point),
+            Object::SmoothTriangle(triangle) => triangle.uv(&point),
+            Object::Mesh(_) | Object::Instance(_) => (0.0, 0.0),
+        };
 
         Intersection {
             distance,
-            point: ray.origin + ray.direction * distance,
+            point,
             obj,
             material,
             normal,
+            geometric_normal,
+            uv,
+            texture: 0,
         }
     }
 }
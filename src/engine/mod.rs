@@ -1,5 +1,17 @@
+pub mod adaptive;
+pub mod aov;
 pub mod bounding_box;
+pub mod bvh;
 pub mod camera;
+pub mod checkpoint;
+pub mod denoiser;
+pub mod film;
+pub mod filter;
+pub mod integrator;
 pub mod intersection;
+pub mod projection;
 pub mod ray;
+pub mod renderer;
+pub mod sampler;
 pub mod scene;
+pub mod tone_mapping;
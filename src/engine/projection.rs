@@ -0,0 +1,20 @@
+/// How the camera maps image positions to rays.
+///
+/// Angles are in degrees and measured across the width of the image, the vertical extent follows
+/// from the image size so that pixels stay square.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Projection {
+    /// Pinhole camera using the field of view of the camera, the only one with depth of field.
+    #[default]
+    Perspective,
+    /// Parallel rays, `width` is the size of the view in scene units.
+    Orthographic { width: f64 },
+    /// Fisheye whose distance to the center is proportional to the angle to the view axis.
+    FisheyeEquidistant { fov: f64 },
+    /// Fisheye preserving areas, like most real fisheye lenses.
+    FisheyeEquisolid { fov: f64 },
+    /// Panorama wrapped on a vertical cylinder around the camera.
+    Cylindrical { fov: f64 },
+    /// Full 360° by 180° panorama, meant for 2:1 images.
+    Equirectangular,
+}
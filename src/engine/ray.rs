@@ -1,10 +1,15 @@
-use crate::light::Emittable;
+use crate::light::{Emittable, Light, LightSample};
 use crate::utils::{
     color::Color,
     math::{point::Point, vector::Vector},
 };
 
-use super::scene::Scene;
+use super::{
+    integrator::RadianceSplit,
+    intersection::Intersection,
+    sampler::{permute, PixelSampler},
+    scene::Scene,
+};
 
 #[derive(Debug, Clone)]
 pub struct Ray {
@@ -26,68 +31,367 @@ impl Ray {
         self.origin + self.direction * distance
     }
 
-    pub fn cast(&self, scene: Scene) -> Color {
+    pub fn cast(&self, scene: &Scene, sampler: &mut impl PixelSampler) -> Color {
+        self.cast_split(scene, sampler).0.total
+    }
+
+    /// Whitted ray tracing, with the light split as in `RadianceSplit`. There is no indirect
+    /// light since only mirror reflections and refractions are followed. The index of the object
+    /// hit and the intersection are returned along.
+    pub fn cast_split(
+        &self,
+        scene: &Scene,
+        sampler: &mut impl PixelSampler,
+    ) -> (RadianceSplit, Option<(usize, Intersection)>) {
+        let zero = Color::zero();
+        let background = RadianceSplit::new(scene.background_at(&self.direction), zero, zero);
         if self.depth > scene.camera.max_bounces {
-            return scene.background;
+            return (background, None);
         }
 
-        //println!("{:?}", self);
-
-        scene
-            .trace(self.clone())
-            .map(|intersection| {
-                let reflected = if Into::<f64>::into(intersection.material.reflection) == 0.0 {
-                    Color::zero()
-                } else {
-                    Ray::new(
-                        intersection.point + intersection.normal * 1e-4,
-                        self.direction.reflect(&intersection.normal),
-                        self.depth + 1,
-                    )
-                    .cast(scene.clone())
-                        * intersection.material.reflection
-                }
-                .clamp();
+        let (object, intersection) = match scene.trace_object(self.clone()) {
+            Some(hit) => hit,
+            None => return (background, None),
+        };
+
+        let reflected = if Into::<f64>::into(intersection.material.reflection) == 0.0 {
+            Color::zero()
+        } else {
+            Ray::new(
+                intersection.point + intersection.normal * 1e-4,
+                self.direction.reflect(&intersection.normal),
+                self.depth + 1,
+            )
+            .cast(scene, sampler)
+                * intersection.material.reflection
+        };
 
-                let light_color: Color = scene
-                    .lights
+        // The sum of every light and the diffuse and specular parts of it
+        let origin = intersection.point + intersection.normal * 1e-4;
+        let ambient = intersection.material.color * intersection.material.ambient;
+        let (light_color, diffuse_color, highlights) = scene.lights.iter().fold(
+            (ambient, ambient, zero),
+            |(color, diffuse_color, highlights), light| {
+                let samples = light_samples(light, intersection.point, sampler);
+                let weight = 1.0 / samples.len() as f64;
+                let (lit, lit_diffuse, lit_specular) = samples
                     .iter()
-                    .filter(|light| {
-                        let shadow_ray = Ray::new(
-                            intersection.point + intersection.normal * 1e-4,
-                            light.direction(intersection.point),
-                            0,
-                        );
-                        scene.trace(shadow_ray).is_none()
+                    .filter(|sample| {
+                        let shadow_ray = Ray::new(origin, sample.direction, 0);
+                        intersection.normal.dot(&sample.direction) > 0.0
+                            && !scene.occluded(shadow_ray, sample.distance)
                     })
                     .fold(
-                        intersection.material.color * intersection.material.ambient,
-                        |color, light| {
-                            let diffuse = intersection
-                                .normal
-                                .dot(&light.direction(intersection.point))
-                                * light.intensity(intersection.point)
+                        (zero, zero, zero),
+                        |(color, diffuse_color, highlights), sample| {
+                            let diffuse = intersection.normal.dot(&sample.direction)
+                                * sample.intensity
                                 * intersection.material.color
                                 * intersection.material.diffuse;
 
                             let specular = self
                                 .direction
                                 .reflect(&intersection.normal)
-                                .dot(&light.direction(intersection.point))
+                                .dot(&sample.direction)
+                                .max(0.0)
                                 .powf(intersection.material.specular_exponent)
-                                * light.intensity(intersection.point)
+                                * sample.intensity
                                 * intersection.material.specular;
 
-                            let color = color + diffuse;
-                            let color = color + specular;
-
-                            color.clamp()
+                            (
+                                color + (diffuse + specular) * weight,
+                                diffuse_color + diffuse * weight,
+                                highlights + specular * weight,
+                            )
                         },
                     );
 
-                light_color + reflected
-            })
-            .unwrap_or(scene.background)
+                (
+                    color + lit,
+                    diffuse_color + lit_diffuse,
+                    highlights + lit_specular,
+                )
+            },
+        );
+
+        let surface = light_color + reflected;
+        let transparency: Color = intersection.material.transparency.into();
+        let (color, direct, specular) = if transparency.max_component() <= 0.0 {
+            (surface, diffuse_color, highlights + reflected)
+        } else {
+            let refracted = self.cast_dielectric(scene, &intersection, sampler);
+            let opacity = 1.0 - transparency;
+            (
+                surface * opacity + refracted * transparency,
+                diffuse_color * opacity,
+                (highlights + reflected) * opacity + refracted * transparency,
+            )
+        };
+
+        // The ray travelled inside the object to reach its boundary
+        let transmittance = if self.interface(&intersection).inside {
+            intersection.material.transmittance(intersection.distance)
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        };
+        let radiance = RadianceSplit {
+            total: color * transmittance,
+            direct: direct * transmittance,
+            indirect: zero,
+            specular: specular * transmittance,
+        };
+        (radiance, Some((object, intersection)))
+    }
+
+    /// Splits the ray between reflection and refraction at a transparent surface.
+    fn cast_dielectric(
+        &self,
+        scene: &Scene,
+        intersection: &Intersection,
+        sampler: &mut impl PixelSampler,
+    ) -> Color {
+        let interface = self.interface(intersection);
+        let reflected = Ray::new(
+            intersection.point + interface.normal * 1e-4,
+            self.direction.reflect(&interface.normal),
+            self.depth + 1,
+        )
+        .cast(scene, sampler);
+
+        match interface.refracted(&self.direction) {
+            Some(direction) if interface.reflectance < 1.0 => {
+                let refracted = Ray::new(
+                    intersection.point - interface.normal * 1e-4,
+                    direction,
+                    self.depth + 1,
+                )
+                .cast(scene, sampler);
+                reflected * interface.reflectance + refracted * (1.0 - interface.reflectance)
+            }
+            _ => reflected,
+        }
+    }
+
+    fn interface(&self, intersection: &Intersection) -> Interface {
+        let material = intersection.material;
+        let inside = intersection.normal.dot(&self.direction) > 0.0;
+        let (normal, eta_i, eta_t) = if inside {
+            (-intersection.normal, material.refraction_index, 1.0)
+        } else {
+            (intersection.normal, 1.0, material.refraction_index)
+        };
+        let cos_i = -self.direction.dot(&normal);
+
+        Interface {
+            normal,
+            eta: eta_i / eta_t,
+            reflectance: material.fresnel.reflectance(cos_i, eta_i, eta_t),
+            inside,
+        }
+    }
+
+    /// Estimates the radiance along the ray with Monte Carlo path tracing.
+    ///
+    /// Diffuse bounces are cosine-weighted, lights are sampled explicitly at every vertex and paths
+    /// longer than `max_bounces` are terminated by Russian roulette. Light intensities are interpreted
+    /// like in `cast` so both integrators agree on direct lighting; the ambient term is dropped since
+    /// indirect light is actually computed. The background acts as a uniform environment, an
+    /// environment light is only added on camera and specular paths since diffuse bounces already
+    /// sample it explicitly.
+    pub fn trace_path(&self, scene: &Scene, sampler: &mut impl PixelSampler) -> Color {
+        self.trace_path_split(scene, sampler).0.total
+    }
+
+    /// Path tracing with the light split as in `RadianceSplit`: after the first surface, the
+    /// light of a path goes to the lobe it bounced off there. The index of the first object hit
+    /// and its intersection are returned along.
+    pub fn trace_path_split(
+        &self,
+        scene: &Scene,
+        sampler: &mut impl PixelSampler,
+    ) -> (RadianceSplit, Option<(usize, Intersection)>) {
+        let zero = Color::zero();
+        let mut radiance = RadianceSplit::new(zero, zero, zero);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = self.clone();
+        let mut diffuse_bounce = false;
+        // Whether the path went through a diffuse bounce at the first surface, `None` before it
+        let mut first_bounce: Option<bool> = None;
+        let mut first_hit = None;
+
+        loop {
+            let intersection = match scene.trace_object(ray.clone()) {
+                Some((object, intersection)) => {
+                    if first_hit.is_none() {
+                        first_hit = Some((object, intersection.clone()));
+                    }
+                    intersection
+                }
+                None => {
+                    if !(diffuse_bounce && scene.environment().is_some()) {
+                        let light = throughput * scene.background_at(&ray.direction);
+                        radiance.total = radiance.total + light;
+                        match first_bounce {
+                            None => radiance.direct = radiance.direct + light,
+                            Some(true) => radiance.indirect = radiance.indirect + light,
+                            Some(false) => radiance.specular = radiance.specular + light,
+                        }
+                    }
+                    break;
+                }
+            };
+
+            let material = intersection.material;
+            let interface = ray.interface(&intersection);
+            let normal = interface.normal;
+            let origin = intersection.point + normal * 1e-4;
+
+            if interface.inside {
+                throughput = throughput * material.transmittance(intersection.distance);
+            }
+
+            // Materials tuned for `cast` may reflect more light than they receive, rescale the
+            // lobes so that paths don't gain energy at every bounce
+            let transparency: Color = material.transparency.into();
+            let opacity = 1.0 - transparency;
+            let albedo = material.color * material.diffuse * opacity;
+            let reflection: Color = opacity * material.reflection;
+            let energy = (albedo.max_component()
+                + reflection.max_component()
+                + transparency.max_component())
+            .max(1.0);
+            let albedo = albedo / energy;
+            let reflection = reflection / energy;
+            let transparency = transparency / energy;
+
+            // Next-event estimation
+            for light in scene.lights.iter() {
+                let samples = light_samples(light, intersection.point, sampler);
+                let weight = 1.0 / samples.len() as f64;
+                for sample in samples {
+                    let cos_theta = normal.dot(&sample.direction);
+                    if cos_theta <= 0.0 {
+                        continue;
+                    }
+                    let shadow_ray = Ray::new(origin, sample.direction, 0);
+                    if scene.occluded(shadow_ray, sample.distance) {
+                        continue;
+                    }
+
+                    let specular = ray
+                        .direction
+                        .reflect(&normal)
+                        .dot(&sample.direction)
+                        .max(0.0)
+                        .powf(material.specular_exponent)
+                        * sample.intensity
+                        * material.specular;
+                    let diffuse = albedo * sample.intensity * cos_theta;
+                    radiance.total = radiance.total + throughput * (diffuse + specular) * weight;
+                    match first_bounce {
+                        None => {
+                            radiance.direct = radiance.direct + throughput * diffuse * weight;
+                            radiance.specular = radiance.specular + throughput * specular * weight;
+                        }
+                        Some(true) => {
+                            radiance.indirect =
+                                radiance.indirect + throughput * (diffuse + specular) * weight;
+                        }
+                        Some(false) => {
+                            radiance.specular =
+                                radiance.specular + throughput * (diffuse + specular) * weight;
+                        }
+                    }
+                }
+            }
+
+            // Pick the next bounce between the diffuse, mirror and transmission lobes
+            let diffuse_weight = albedo.max_component();
+            let reflection_weight = reflection.max_component();
+            let transmission_weight = transparency.max_component();
+            let total_weight = diffuse_weight + reflection_weight + transmission_weight;
+            if total_weight <= 0.0 {
+                break;
+            }
+
+            let lobe = sampler.get_1d() * total_weight;
+            diffuse_bounce = lobe >= reflection_weight + transmission_weight;
+            first_bounce.get_or_insert(diffuse_bounce);
+            let (origin, direction) = if lobe < reflection_weight {
+                throughput = throughput * reflection * (total_weight / reflection_weight);
+                (origin, ray.direction.reflect(&normal))
+            } else if lobe < reflection_weight + transmission_weight {
+                throughput = throughput * transparency * (total_weight / transmission_weight);
+                // Choosing between reflection and refraction by the Fresnel term cancels it out
+                match interface.refracted(&ray.direction) {
+                    Some(direction) if sampler.get_1d() >= interface.reflectance => {
+                        (intersection.point - normal * 1e-4, direction)
+                    }
+                    _ => (origin, ray.direction.reflect(&normal)),
+                }
+            } else {
+                throughput = throughput * albedo * (total_weight / diffuse_weight);
+                {
+                    let (u, v) = sampler.get_2d();
+                    (origin, normal.cosine_hemisphere(u, v))
+                }
+            };
+
+            if ray.depth >= scene.camera.max_bounces {
+                let survival = throughput.max_component().clamp(0.05, 0.95);
+                if sampler.get_1d() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+
+            ray = Ray::new(origin, direction, ray.depth + 1);
+        }
+
+        (radiance, first_hit)
+    }
+}
+
+/// Samples of `light` seen from `point`, stratified as a latin hypercube so that soft shadows
+/// converge with few shadow rays. The strata are shuffled and jittered with three dimensions of
+/// `sampler`, delta lights get a single sample without using it.
+fn light_samples(light: &Light, point: Point, sampler: &mut impl PixelSampler) -> Vec<LightSample> {
+    if light.is_delta() {
+        return vec![light.sample(point, 0.5, 0.5)];
+    }
+    let count = light.samples();
+    if count == 1 {
+        let (u, v) = sampler.get_2d();
+        return vec![light.sample(point, u, v)];
+    }
+
+    let (u_jitter, v_jitter) = sampler.get_2d();
+    let seed = (sampler.get_1d() * u32::MAX as f64) as u32;
+    (0..count)
+        .map(|column| {
+            let row = permute(column, count, seed);
+            let u = (column as f64 + u_jitter) / count as f64;
+            let v = (row as f64 + v_jitter) / count as f64;
+            light.sample(point, u, v)
+        })
+        .collect()
+}
+
+/// Orientation of a surface relative to a ray hitting it.
+struct Interface {
+    /// Surface normal on the side the ray comes from.
+    normal: Vector,
+    /// Ratio of the incident over the transmitted refraction indices.
+    eta: f64,
+    /// Fresnel reflectance, 1 on total internal reflection.
+    reflectance: f64,
+    /// Whether the ray hits the surface from inside the object.
+    inside: bool,
+}
+
+impl Interface {
+    fn refracted(&self, direction: &Vector) -> Option<Vector> {
+        direction.refract(&self.normal, self.eta)
     }
 }
 
@@ -0,0 +1,484 @@
+use std::{
+    fmt, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::utils::color::Color;
+
+use super::{
+    aov::{Aov, AovSample, AovTile},
+    checkpoint::{Checkpoint, CheckpointError},
+    film::{Film, FilmTile, PixelStatistics},
+    integrator::RadianceSplit,
+    sampler::PixelSampler,
+    scene::Scene,
+};
+
+/// Rectangle of the image rendered by a thread in one go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Progress reported to the callback of a `Renderer`.
+#[derive(Debug)]
+pub enum RenderEvent<'a> {
+    /// A tile of a pass is done, `done` out of `total` tiles of the pass are. Sent from the worker
+    /// threads, in no particular order.
+    TileDone {
+        pass: u32,
+        tile: Tile,
+        done: usize,
+        total: usize,
+    },
+    /// Every tile of a pass is done, `film` holds the image refined so far and `samples` is the
+    /// number of samples of the most sampled pixels.
+    PassDone {
+        pass: u32,
+        samples: u32,
+        film: &'a Film,
+    },
+    /// The accumulated samples were written to the checkpoint file.
+    Checkpoint {
+        path: &'a Path,
+        result: &'a io::Result<()>,
+    },
+}
+
+/// Shared flag stopping a render from another thread. Tiles already started are finished, the
+/// others are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+type Callback<'a> = Arc<dyn Fn(RenderEvent<'_>) + Send + Sync + 'a>;
+
+/// Renders a scene tile by tile in progressive passes, each pass refining the whole image.
+///
+/// Without adaptive sampling every pass adds `samples_per_pass` samples to each pixel until the
+/// camera's `anti_aliasing` is reached, with adaptive sampling the passes are the ones refining the
+/// noisy pixels. The image only depends on the samples taken, not on the tiles or the threads.
+///
+/// The accumulated samples can be saved to a checkpoint file and resumed later, even with a higher
+/// sample count. Every pixel continues from its own sample count, so with the random, Halton,
+/// Sobol and blue noise samplers a resumed render matches a render done in one go, up to rounding.
+/// Stratified samples depend on the total count, raising it on resume gives a valid but different
+/// image.
+///
+/// Progress is reported to a callback, which can forward it to a channel:
+///
+/// ```no_run
+/// # use rusttracer::engine::{renderer::{Renderer, RenderEvent}, scene::Scene};
+/// # let scene = Scene::default();
+/// let (sender, receiver) = std::sync::mpsc::channel();
+/// let renderer = Renderer::new(&scene).with_callback(move |event| {
+///     if let RenderEvent::PassDone { pass, .. } = event {
+///         sender.send(pass).ok();
+///     }
+/// });
+/// ```
+#[derive(Clone)]
+pub struct Renderer<'a> {
+    pub scene: &'a Scene,
+    /// Side of the tiles in pixels.
+    pub tile_size: u32,
+    /// Samples added to every pixel by each pass when sampling is not adaptive. When `None`, all of
+    /// them are taken in a single pass, or one per pass with a `time_budget` so that it can stop
+    /// the render.
+    pub samples_per_pass: Option<u32>,
+    /// No pass is started once this much time has elapsed and the current pass stops at the next
+    /// tile. The first pass is always completed so that the image is never missing parts.
+    pub time_budget: Option<Duration>,
+    pub cancel: CancelToken,
+    /// File the accumulated samples are saved to, after a pass when `checkpoint_interval` has
+    /// elapsed since the last save and when the render stops.
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Duration,
+    /// AOVs rendered into the `aovs` of the film. They are not saved in checkpoints, the AOVs of
+    /// a resumed render only average the samples taken after resuming.
+    pub aovs: Vec<Aov>,
+    callback: Option<Callback<'a>>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(scene: &'a Scene) -> Renderer<'a> {
+        Renderer {
+            scene,
+            tile_size: 32,
+            samples_per_pass: None,
+            time_budget: None,
+            cancel: CancelToken::default(),
+            checkpoint: None,
+            checkpoint_interval: Duration::from_secs(60),
+            aovs: Vec::new(),
+            callback: None,
+        }
+    }
+
+    pub fn with_tile_size(&self, tile_size: u32) -> Renderer<'a> {
+        Renderer {
+            tile_size: tile_size.max(1),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_samples_per_pass(&self, samples_per_pass: u32) -> Renderer<'a> {
+        Renderer {
+            samples_per_pass: Some(samples_per_pass.max(1)),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_time_budget(&self, time_budget: Duration) -> Renderer<'a> {
+        Renderer {
+            time_budget: Some(time_budget),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_cancel_token(&self, cancel: CancelToken) -> Renderer<'a> {
+        Renderer {
+            cancel,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_checkpoint(&self, path: impl Into<PathBuf>, interval: Duration) -> Renderer<'a> {
+        Renderer {
+            checkpoint: Some(path.into()),
+            checkpoint_interval: interval,
+            ..self.clone()
+        }
+    }
+
+    pub fn with_aovs(&self, aovs: &[Aov]) -> Renderer<'a> {
+        Renderer {
+            aovs: aovs.to_vec(),
+            ..self.clone()
+        }
+    }
+
+    pub fn with_callback(
+        &self,
+        callback: impl Fn(RenderEvent<'_>) + Send + Sync + 'a,
+    ) -> Renderer<'a> {
+        Renderer {
+            callback: Some(Arc::new(callback)),
+            ..self.clone()
+        }
+    }
+
+    /// Renders into a new film of the given size.
+    pub fn render(&self, width: u32, height: u32) -> Film {
+        let mut film = Film::new(width, height);
+        self.render_into(&mut film);
+        film
+    }
+
+    /// Renders until every pass is done, the render is cancelled or the time budget is spent. The
+    /// film holds the image and the sample count of every pixel.
+    pub fn render_into(&self, film: &mut Film) {
+        self.run(film, Checkpoint::new(self.scene, film.width, film.height));
+    }
+
+    /// Continues the render saved in `checkpoint`, which must come from the same scene. The
+    /// passes left to reach the camera's sample count are rendered, none if it was already met.
+    pub fn resume(&self, checkpoint: Checkpoint) -> Result<Film, CheckpointError> {
+        if !checkpoint.matches(self.scene) {
+            return Err(CheckpointError::SceneMismatch);
+        }
+        let mut film = Film::new(checkpoint.width(), checkpoint.height());
+        self.run(&mut film, checkpoint);
+        Ok(film)
+    }
+
+    fn run(&self, film: &mut Film, mut state: Checkpoint) {
+        let start = Instant::now();
+        let (width, height) = (film.width, film.height);
+        let camera = &self.scene.camera;
+        let tiles = self.tiles(width, height);
+        let mut aovs = AovTile::new(&self.aovs, 0, 0, width, height);
+        let mut saved = Instant::now();
+        let mut first = true;
+
+        state.accumulated.develop(film);
+        film.samples = state.statistics.iter().map(|pixel| pixel.count).collect();
+        loop {
+            let samples = state.samples;
+            let (indices, total, selected) = match camera.adaptive {
+                None => {
+                    let total = camera.anti_aliasing.max(1);
+                    let step = match (self.samples_per_pass, self.time_budget) {
+                        (Some(step), _) => step,
+                        (None, Some(_)) => 1,
+                        (None, None) => total,
+                    };
+                    (samples..(samples + step).min(total), total, None)
+                }
+                Some(adaptive) => {
+                    let total = adaptive.max_samples.max(1);
+                    if samples == 0 {
+                        (0..adaptive.min_samples.clamp(1, total), total, None)
+                    } else {
+                        let noisy = adaptive.noisy_pixels(&state.statistics, width, height);
+                        if !noisy.contains(&true) {
+                            break;
+                        }
+                        (samples..(samples * 2).min(total), total, Some(noisy))
+                    }
+                }
+            };
+            if indices.is_empty() || (!first && self.should_stop(start)) {
+                break;
+            }
+
+            let finished = self.render_pass(
+                state.pass,
+                &tiles,
+                &mut state.accumulated,
+                &mut state.statistics,
+                &mut aovs,
+                indices.clone(),
+                total,
+                selected.as_deref(),
+                start,
+                first,
+            );
+
+            state.accumulated.develop(film);
+            aovs.develop(film);
+            film.samples = state.statistics.iter().map(|pixel| pixel.count).collect();
+            if let Some(callback) = &self.callback {
+                callback(RenderEvent::PassDone {
+                    pass: state.pass,
+                    samples: indices.end,
+                    film,
+                });
+            }
+            if !finished {
+                break;
+            }
+            state.samples = indices.end;
+            state.pass += 1;
+            first = false;
+            if saved.elapsed() >= self.checkpoint_interval {
+                self.save_checkpoint(&state);
+                saved = Instant::now();
+            }
+        }
+        self.save_checkpoint(&state);
+    }
+
+    fn save_checkpoint(&self, state: &Checkpoint) {
+        if let Some(path) = &self.checkpoint {
+            let result = state.save(path);
+            if let Some(callback) = &self.callback {
+                callback(RenderEvent::Checkpoint {
+                    path,
+                    result: &result,
+                });
+            }
+        }
+    }
+
+    fn should_stop(&self, start: Instant) -> bool {
+        self.cancel.is_cancelled()
+            || self
+                .time_budget
+                .is_some_and(|budget| start.elapsed() >= budget)
+    }
+
+    fn tiles(&self, width: u32, height: u32) -> Vec<Tile> {
+        let size = self.tile_size.max(1);
+        (0..height)
+            .step_by(size as usize)
+            .flat_map(|y| {
+                (0..width).step_by(size as usize).map(move |x| Tile {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                })
+            })
+            .collect()
+    }
+
+    /// Takes the samples `indices` out of `samples` of the selected pixels, returns whether every
+    /// tile was rendered. Pixels already past the start of `indices` only take the samples they
+    /// are missing. The tiles of the first pass of a render are only skipped when cancelled.
+    #[allow(clippy::too_many_arguments)]
+    fn render_pass(
+        &self,
+        pass: u32,
+        tiles: &[Tile],
+        accumulated: &mut FilmTile,
+        statistics: &mut [PixelStatistics],
+        aovs: &mut AovTile,
+        indices: Range<u32>,
+        samples: u32,
+        selected: Option<&[bool]>,
+        start: Instant,
+        first: bool,
+    ) -> bool {
+        let (width, height) = (accumulated.width, accumulated.height);
+        let done = AtomicUsize::new(0);
+
+        let rendered = tiles
+            .par_iter()
+            .map(|tile| {
+                let stop = if first {
+                    self.cancel.is_cancelled()
+                } else {
+                    self.should_stop(start)
+                };
+                if stop {
+                    return None;
+                }
+
+                let result = self.render_tile(
+                    tile,
+                    (width, height),
+                    statistics,
+                    indices.clone(),
+                    samples,
+                    selected,
+                );
+                if let Some(callback) = &self.callback {
+                    callback(RenderEvent::TileDone {
+                        pass,
+                        tile: *tile,
+                        done: done.fetch_add(1, Ordering::Relaxed) + 1,
+                        total: tiles.len(),
+                    });
+                }
+                Some((*tile, result))
+            })
+            .collect::<Vec<_>>();
+
+        let finished = rendered.iter().all(Option::is_some);
+        for (tile, (film_tile, tile_statistics, aov_tile)) in rendered.into_iter().flatten() {
+            accumulated.merge(&film_tile);
+            aovs.merge(&aov_tile);
+            for (row, chunk) in tile_statistics.chunks(tile.width as usize).enumerate() {
+                let offset = ((tile.y + row as u32) * width + tile.x) as usize;
+                statistics[offset..offset + chunk.len()].copy_from_slice(chunk);
+            }
+        }
+        finished
+    }
+
+    /// Samples the pixels of `tile`, splatted on a film tile extended by the filter radius. Returns
+    /// it with the updated statistics of the pixels of the tile and the AOVs.
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        image_size: (u32, u32),
+        statistics: &[PixelStatistics],
+        indices: Range<u32>,
+        samples: u32,
+        selected: Option<&[bool]>,
+    ) -> (FilmTile, Vec<PixelStatistics>, AovTile) {
+        let camera = &self.scene.camera;
+        let (width, height) = image_size;
+        let filter = camera.filter;
+        let margin = filter.radius().ceil() as u32;
+        let (left, top) = (tile.x.saturating_sub(margin), tile.y.saturating_sub(margin));
+        let right = (tile.x + tile.width + margin).min(width);
+        let bottom = (tile.y + tile.height + margin).min(height);
+        let mut film_tile = FilmTile::new(left, top, right - left, bottom - top);
+        let mut aov_tile = AovTile::new(&self.aovs, left, top, right - left, bottom - top);
+        let mut tile_statistics = Vec::with_capacity((tile.width * tile.height) as usize);
+        let mut sampler = camera.sampler.clone();
+
+        for y in tile.y..tile.y + tile.height {
+            for x in tile.x..tile.x + tile.width {
+                let index = (y * width + x) as usize;
+                let mut pixel = statistics[index];
+                if selected.is_none_or(|selected| selected[index]) {
+                    for sample in pixel.count.max(indices.start)..indices.end {
+                        sampler.start_pixel_sample((x, y), sample, samples);
+                        // A lone sample stays at the center of the pixel
+                        let (x_offset, y_offset) = if samples == 1 {
+                            (0.5, 0.5)
+                        } else {
+                            sampler.get_2d()
+                        };
+                        let position = (x as f64 + x_offset, y as f64 + y_offset);
+                        let ray = camera.sample_ray(
+                            position,
+                            (width as usize, height as usize),
+                            &mut sampler,
+                        );
+                        let color = match ray {
+                            Some(ray) if aov_tile.is_empty() => {
+                                camera.integrator.radiance(&ray, self.scene, &mut sampler)
+                            }
+                            Some(ray) => {
+                                let (radiance, hit) = camera.integrator.radiance_split(
+                                    &ray,
+                                    self.scene,
+                                    &mut sampler,
+                                );
+                                let aov = AovSample::new(self.scene, hit.as_ref(), radiance);
+                                aov_tile.add_sample(position, &aov, &filter);
+                                radiance.total
+                            }
+                            // Outside of the image circle of a fisheye
+                            None => {
+                                let black = Color::zero();
+                                if !aov_tile.is_empty() {
+                                    let radiance = RadianceSplit::new(black, black, black);
+                                    let aov = AovSample::miss(radiance);
+                                    aov_tile.add_sample(position, &aov, &filter);
+                                }
+                                black
+                            }
+                        };
+                        film_tile.add_sample(position, color, &filter);
+                        pixel.add(color.relative_luminance());
+                    }
+                }
+                tile_statistics.push(pixel);
+            }
+        }
+        (film_tile, tile_statistics, aov_tile)
+    }
+}
+
+impl fmt::Debug for Renderer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Renderer")
+            .field("tile_size", &self.tile_size)
+            .field("samples_per_pass", &self.samples_per_pass)
+            .field("time_budget", &self.time_budget)
+            .field("cancel", &self.cancel)
+            .field("checkpoint", &self.checkpoint)
+            .field("checkpoint_interval", &self.checkpoint_interval)
+            .field("aovs", &self.aovs)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::OnceLock;
+
+use super::{fraction, hash, owen_scramble, sobol_2d, PixelSampler, SampleIndex};
+
+/// Side of the tiled blue noise mask.
+const SIZE: usize = 64;
+
+/// Scrambled Sobol points shared by every pixel and shifted per pixel by a blue noise mask, which
+/// pushes the remaining error to high frequencies: at low sample counts the noise looks like a
+/// fine grain rather than blotches. Each dimension reads the mask at a different offset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlueNoiseSampler {
+    pub seed: u64,
+    sample: SampleIndex,
+}
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u64) -> BlueNoiseSampler {
+        BlueNoiseSampler {
+            seed,
+            sample: SampleIndex::default(),
+        }
+    }
+
+    pub fn with_seed(&self, seed: u64) -> BlueNoiseSampler {
+        BlueNoiseSampler {
+            seed,
+            ..self.clone()
+        }
+    }
+
+    fn point(&self, dimension: u32) -> (f64, f64) {
+        let seed = hash(&[self.seed, dimension as u64]);
+        let index = owen_scramble(self.sample.index, seed as u32);
+        let (x, y) = sobol_2d(index);
+        let (x, y) = (
+            fraction(owen_scramble(x, (seed >> 32) as u32)),
+            fraction(owen_scramble(y, super::mix(seed) as u32)),
+        );
+        let shift = (self.mask(seed), self.mask(super::mix(seed)));
+        ((x + shift.0).fract(), (y + shift.1).fract())
+    }
+
+    /// Value of the mask at the current pixel, with the tile offset chosen by `seed`.
+    fn mask(&self, seed: u64) -> f64 {
+        let (x, y) = self.sample.pixel;
+        let x = (x as usize + (seed as usize % SIZE)) % SIZE;
+        let y = (y as usize + ((seed >> 32) as usize % SIZE)) % SIZE;
+        mask()[y * SIZE + x] as f64
+    }
+}
+
+impl PixelSampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32, count: u32) {
+        self.sample = SampleIndex::new(pixel, index, count);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.sample.advance(1);
+        self.point(dimension).0
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.sample.advance(2);
+        self.point(dimension)
+    }
+}
+
+/// Blue noise mask tiling the plane, uniformly distributed in [0, 1). Generated on first use.
+fn mask() -> &'static [f32] {
+    static MASK: OnceLock<Vec<f32>> = OnceLock::new();
+    MASK.get_or_init(void_and_cluster)
+}
+
+/// Ulichney's void-and-cluster method: pixels are ranked by repeatedly filling the largest void
+/// of a pattern, measured with a toroidal Gaussian energy.
+fn void_and_cluster() -> Vec<f32> {
+    let cells = SIZE * SIZE;
+    let sigma = 1.5;
+    let kernel = (0..cells)
+        .map(|index| {
+            let (dx, dy) = (index % SIZE, index / SIZE);
+            let (dx, dy) = (dx.min(SIZE - dx) as f64, dy.min(SIZE - dy) as f64);
+            (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        })
+        .collect::<Vec<_>>();
+
+    let toggle = |energy: &mut [f64], pattern: &mut [bool], cell: usize| {
+        pattern[cell] = !pattern[cell];
+        let sign = if pattern[cell] { 1.0 } else { -1.0 };
+        let (x, y) = (cell % SIZE, cell / SIZE);
+        for (index, energy) in energy.iter_mut().enumerate() {
+            let dx = (index % SIZE + SIZE - x) % SIZE;
+            let dy = (index / SIZE + SIZE - y) % SIZE;
+            *energy += sign * kernel[dy * SIZE + dx];
+        }
+    };
+    // Tightest cluster among the set cells, or largest void among the empty ones
+    let extreme = |energy: &[f64], pattern: &[bool], set: bool| {
+        let candidates = (0..cells).filter(|&cell| pattern[cell] == set);
+        if set {
+            candidates.max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        } else {
+            candidates.min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        }
+    };
+
+    // Random initial pattern covering a tenth of the cells, spread evenly by moving points from
+    // the tightest cluster to the largest void until it is stable
+    let mut energy = vec![0.0; cells];
+    let mut pattern = vec![false; cells];
+    let mut seed = 0;
+    while pattern.iter().filter(|&&set| set).count() < cells / 10 {
+        seed += 1;
+        let cell = (hash(&[seed]) % cells as u64) as usize;
+        if !pattern[cell] {
+            toggle(&mut energy, &mut pattern, cell);
+        }
+    }
+    for _ in 0..cells {
+        let cluster = extreme(&energy, &pattern, true).unwrap();
+        toggle(&mut energy, &mut pattern, cluster);
+        let void = extreme(&energy, &pattern, false).unwrap();
+        toggle(&mut energy, &mut pattern, void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; cells];
+    let initial = (energy.clone(), pattern.clone());
+    let points = pattern.iter().filter(|&&set| set).count();
+
+    // The initial points are ranked by removing the tightest cluster first
+    for rank in (0..points).rev() {
+        let cluster = extreme(&energy, &pattern, true).unwrap();
+        toggle(&mut energy, &mut pattern, cluster);
+        ranks[cluster] = rank;
+    }
+
+    // The others by filling the largest void, which is also the tightest cluster of empty cells
+    let (mut energy, mut pattern) = initial;
+    for rank in points..cells {
+        let void = extreme(&energy, &pattern, false).unwrap();
+        toggle(&mut energy, &mut pattern, void);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| ((rank as f64 + 0.5) / cells as f64) as f32)
+        .collect()
+}
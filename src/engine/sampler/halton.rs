@@ -0,0 +1,75 @@
+use super::{permute, PixelSampler, SampleIndex};
+
+/// Bases of the Halton dimensions, later dimensions fall back to independent numbers.
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Halton sequence indexed by the sample, with the digits of every dimension randomly permuted
+/// per pixel so that neighbouring pixels do not repeat the same pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HaltonSampler {
+    pub seed: u64,
+    sample: SampleIndex,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> HaltonSampler {
+        HaltonSampler {
+            seed,
+            sample: SampleIndex::default(),
+        }
+    }
+
+    pub fn with_seed(&self, seed: u64) -> HaltonSampler {
+        HaltonSampler {
+            seed,
+            ..self.clone()
+        }
+    }
+
+    fn value(&self, dimension: u32) -> f64 {
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => {
+                let seed = self.sample.pixel_seed(self.seed, dimension);
+                scrambled_radical_inverse(self.sample.index, base, seed)
+            }
+            None => self.sample.random(self.seed, dimension),
+        }
+    }
+}
+
+/// Digits of `index` in `base` mirrored around the point, each digit position going through its
+/// own permutation. Digits are permuted until they no longer change the result.
+fn scrambled_radical_inverse(mut index: u32, base: u32, seed: u64) -> f64 {
+    let inverse = 1.0 / base as f64;
+    let mut factor = inverse;
+    let mut result = 0.0;
+    let mut position = 0;
+    while factor > 1e-16 {
+        let digit_seed = super::hash(&[seed, position]) as u32;
+        let digit = permute(index % base, base, digit_seed);
+        result += digit as f64 * factor;
+        index /= base;
+        factor *= inverse;
+        position += 1;
+    }
+    result.min(1.0 - f64::EPSILON)
+}
+
+impl PixelSampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32, count: u32) {
+        self.sample = SampleIndex::new(pixel, index, count);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.sample.advance(1);
+        self.value(dimension)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.sample.advance(2);
+        (self.value(dimension), self.value(dimension + 1))
+    }
+}
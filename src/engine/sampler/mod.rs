@@ -0,0 +1,345 @@
+use self::{
+    blue_noise::BlueNoiseSampler, halton::HaltonSampler, random::RandomSampler,
+    sobol::SobolSampler, stratified::StratifiedSampler,
+};
+
+pub mod blue_noise;
+pub mod halton;
+pub mod random;
+pub mod sobol;
+pub mod stratified;
+
+/// Source of the uniform numbers used by a pixel sample.
+///
+/// Every random decision of a sample (pixel jitter, lens, lights, bounces) reads the next
+/// dimension, so that low discrepancy sequences spread each decision over the samples of a pixel.
+/// The numbers only depend on the seed, the pixel, the sample index and the dimension: each pixel
+/// has its own stream and renders are reproducible whatever the number of threads.
+pub trait PixelSampler {
+    /// Moves to sample `index` of the `count` taken in `pixel`, restarting at the first dimension.
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32, count: u32);
+    /// Next dimension of the current sample, in [0, 1).
+    fn get_1d(&mut self) -> f64;
+    /// Next two dimensions of the current sample, in [0, 1).
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sampler {
+    Random(RandomSampler),
+    Stratified(StratifiedSampler),
+    Halton(HaltonSampler),
+    Sobol(SobolSampler),
+    BlueNoise(BlueNoiseSampler),
+}
+
+impl Sampler {
+    pub fn new_random(seed: u64) -> Sampler {
+        Sampler::Random(RandomSampler::new(seed))
+    }
+
+    pub fn new_stratified(seed: u64) -> Sampler {
+        Sampler::Stratified(StratifiedSampler::new(seed))
+    }
+
+    pub fn new_halton(seed: u64) -> Sampler {
+        Sampler::Halton(HaltonSampler::new(seed))
+    }
+
+    pub fn new_sobol(seed: u64) -> Sampler {
+        Sampler::Sobol(SobolSampler::new(seed))
+    }
+
+    pub fn new_blue_noise(seed: u64) -> Sampler {
+        Sampler::BlueNoise(BlueNoiseSampler::new(seed))
+    }
+
+    /// Seed every pixel stream is derived from, the same seed always gives the same image.
+    pub fn seed(&self) -> u64 {
+        match self {
+            Sampler::Random(sampler) => sampler.seed,
+            Sampler::Stratified(sampler) => sampler.seed,
+            Sampler::Halton(sampler) => sampler.seed,
+            Sampler::Sobol(sampler) => sampler.seed,
+            Sampler::BlueNoise(sampler) => sampler.seed,
+        }
+    }
+
+    pub fn with_seed(&self, seed: u64) -> Sampler {
+        match self {
+            Sampler::Random(sampler) => sampler.with_seed(seed).into(),
+            Sampler::Stratified(sampler) => sampler.with_seed(seed).into(),
+            Sampler::Halton(sampler) => sampler.with_seed(seed).into(),
+            Sampler::Sobol(sampler) => sampler.with_seed(seed).into(),
+            Sampler::BlueNoise(sampler) => sampler.with_seed(seed).into(),
+        }
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Sampler {
+        Sampler::new_sobol(0)
+    }
+}
+
+impl PixelSampler for Sampler {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32, count: u32) {
+        match self {
+            Sampler::Random(sampler) => sampler.start_pixel_sample(pixel, index, count),
+            Sampler::Stratified(sampler) => sampler.start_pixel_sample(pixel, index, count),
+            Sampler::Halton(sampler) => sampler.start_pixel_sample(pixel, index, count),
+            Sampler::Sobol(sampler) => sampler.start_pixel_sample(pixel, index, count),
+            Sampler::BlueNoise(sampler) => sampler.start_pixel_sample(pixel, index, count),
+        }
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        match self {
+            Sampler::Random(sampler) => sampler.get_1d(),
+            Sampler::Stratified(sampler) => sampler.get_1d(),
+            Sampler::Halton(sampler) => sampler.get_1d(),
+            Sampler::Sobol(sampler) => sampler.get_1d(),
+            Sampler::BlueNoise(sampler) => sampler.get_1d(),
+        }
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        match self {
+            Sampler::Random(sampler) => sampler.get_2d(),
+            Sampler::Stratified(sampler) => sampler.get_2d(),
+            Sampler::Halton(sampler) => sampler.get_2d(),
+            Sampler::Sobol(sampler) => sampler.get_2d(),
+            Sampler::BlueNoise(sampler) => sampler.get_2d(),
+        }
+    }
+}
+
+impl From<RandomSampler> for Sampler {
+    fn from(sampler: RandomSampler) -> Sampler {
+        Sampler::Random(sampler)
+    }
+}
+
+impl From<StratifiedSampler> for Sampler {
+    fn from(sampler: StratifiedSampler) -> Sampler {
+        Sampler::Stratified(sampler)
+    }
+}
+
+impl From<HaltonSampler> for Sampler {
+    fn from(sampler: HaltonSampler) -> Sampler {
+        Sampler::Halton(sampler)
+    }
+}
+
+impl From<SobolSampler> for Sampler {
+    fn from(sampler: SobolSampler) -> Sampler {
+        Sampler::Sobol(sampler)
+    }
+}
+
+impl From<BlueNoiseSampler> for Sampler {
+    fn from(sampler: BlueNoiseSampler) -> Sampler {
+        Sampler::BlueNoise(sampler)
+    }
+}
+
+/// Current position of a sampler: the pixel, the sample and the next dimension to be read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SampleIndex {
+    pub pixel: (u32, u32),
+    pub index: u32,
+    pub count: u32,
+    pub dimension: u32,
+}
+
+impl SampleIndex {
+    pub fn new(pixel: (u32, u32), index: u32, count: u32) -> SampleIndex {
+        SampleIndex {
+            pixel,
+            index,
+            count,
+            dimension: 0,
+        }
+    }
+
+    /// Returns the next dimension and skips `count` of them.
+    pub fn advance(&mut self, count: u32) -> u32 {
+        let dimension = self.dimension;
+        self.dimension += count;
+        dimension
+    }
+
+    /// Seed unique to the pixel and dimension, shared by every sample of the pixel.
+    pub fn pixel_seed(&self, seed: u64, dimension: u32) -> u64 {
+        hash(&[
+            seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            dimension as u64,
+        ])
+    }
+
+    /// Independent uniform number for a dimension of the current sample.
+    pub fn random(&self, seed: u64, dimension: u32) -> f64 {
+        to_unit(hash(&[
+            seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.index as u64,
+            dimension as u64,
+        ]))
+    }
+}
+
+/// Mixes the bits of `value`, the finalizer of SplitMix64.
+pub fn mix(mut value: u64) -> u64 {
+    value ^= value >> 30;
+    value = value.wrapping_mul(0xbf58476d1ce4e5b9);
+    value ^= value >> 27;
+    value = value.wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+/// Hashes a list of integers into well distributed bits.
+pub fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |hash, value| {
+        mix(hash ^ mix(value.wrapping_add(0x9e3779b97f4a7c15)))
+    })
+}
+
+/// Uniform number in [0, 1) from the high bits of `bits`.
+pub fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Element at `index` of a random permutation of `0..length` chosen by `seed`.
+///
+/// Kensler's hashed permutation, it does not need to store the permutation.
+pub fn permute(mut index: u32, length: u32, seed: u32) -> u32 {
+    if length <= 1 {
+        return 0;
+    }
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < length {
+            break;
+        }
+    }
+    index.wrapping_add(seed) % length
+}
+
+/// Nested uniform (Owen) scrambling of the bits of `value` after the binary point, computed with
+/// Laine and Karras' hash as proposed by Burley.
+pub fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut value = value.reverse_bits();
+    value = value.wrapping_add(seed);
+    value ^= value.wrapping_mul(0x6c50b47c);
+    value ^= value.wrapping_mul(0xb82f1e52);
+    value ^= value.wrapping_mul(0xc7afe638);
+    value ^= value.wrapping_mul(0x8d22f6e6);
+    value.reverse_bits()
+}
+
+/// First two dimensions of the Sobol sequence as fixed point fractions.
+pub fn sobol_2d(index: u32) -> (u32, u32) {
+    let mut y = 0;
+    let mut direction = 1u32 << 31;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= direction;
+        }
+        bits >>= 1;
+        direction ^= direction >> 1;
+    }
+    (index.reverse_bits(), y)
+}
+
+/// Fixed point fraction to a number in [0, 1).
+pub fn fraction(value: u32) -> f64 {
+    value as f64 / (1u64 << 32) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samplers() -> [Sampler; 5] {
+        [
+            Sampler::new_random(7),
+            Sampler::new_stratified(7),
+            Sampler::new_halton(7),
+            Sampler::new_sobol(7),
+            Sampler::new_blue_noise(7),
+        ]
+    }
+
+    fn dimensions(sampler: &mut Sampler, pixel: (u32, u32), index: u32) -> Vec<f64> {
+        sampler.start_pixel_sample(pixel, index, 16);
+        let (x, y) = sampler.get_2d();
+        let mut values = vec![x, y];
+        values.extend((0..6).map(|_| sampler.get_1d()));
+        values
+    }
+
+    #[test]
+    fn samples_are_in_range_and_repeatable() {
+        for mut sampler in samplers() {
+            for index in 0..16 {
+                let values = dimensions(&mut sampler, (3, 5), index);
+                assert!(values.iter().all(|value| (0.0..1.0).contains(value)));
+                // Reading other pixels in between must not change the stream
+                dimensions(&mut sampler, (4, 5), index);
+                assert_eq!(dimensions(&mut sampler, (3, 5), index), values);
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_samples_cover_every_stratum() {
+        for mut sampler in [Sampler::new_stratified(7), Sampler::new_sobol(7)] {
+            let mut strata = [false; 16];
+            for index in 0..16 {
+                sampler.start_pixel_sample((3, 5), index, 16);
+                let (x, y) = sampler.get_2d();
+                strata[(y * 4.0) as usize * 4 + (x * 4.0) as usize] = true;
+            }
+            assert!(strata.iter().all(|&covered| covered), "{:?}", sampler);
+        }
+    }
+
+    #[test]
+    fn permutations_are_bijective() {
+        for length in [1, 2, 7, 16, 100] {
+            let mut seen = vec![false; length as usize];
+            for index in 0..length {
+                seen[permute(index, length, 0xdead_beef) as usize] = true;
+            }
+            assert!(seen.iter().all(|&seen| seen));
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use super::{PixelSampler, SampleIndex};
+
+/// Independent uniform numbers, the noisiest sampler but free of any correlation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomSampler {
+    pub seed: u64,
+    sample: SampleIndex,
+}
+
+impl RandomSampler {
+    pub fn new(seed: u64) -> RandomSampler {
+        RandomSampler {
+            seed,
+            sample: SampleIndex::default(),
+        }
+    }
+
+    pub fn with_seed(&self, seed: u64) -> RandomSampler {
+        RandomSampler {
+            seed,
+            ..self.clone()
+        }
+    }
+}
+
+impl PixelSampler for RandomSampler {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32, count: u32) {
+        self.sample = SampleIndex::new(pixel, index, count);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.sample.advance(1);
+        self.sample.random(self.seed, dimension)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.sample.advance(2);
+        (
+            self.sample.random(self.seed, dimension),
+            self.sample.random(self.seed, dimension + 1),
+        )
+    }
+}
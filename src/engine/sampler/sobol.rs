@@ -0,0 +1,55 @@
+use super::{fraction, owen_scramble, sobol_2d, PixelSampler, SampleIndex};
+
+/// Owen scrambled Sobol points, padded: every 1D or 2D dimension is its own scrambled and shuffled
+/// copy of the first Sobol dimensions, so that any number of dimensions keeps the stratification
+/// of the first two.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SobolSampler {
+    pub seed: u64,
+    sample: SampleIndex,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> SobolSampler {
+        SobolSampler {
+            seed,
+            sample: SampleIndex::default(),
+        }
+    }
+
+    pub fn with_seed(&self, seed: u64) -> SobolSampler {
+        SobolSampler {
+            seed,
+            ..self.clone()
+        }
+    }
+
+    /// Scrambled Sobol point for a dimension, the index is shuffled as well so that dimensions are
+    /// not correlated with each other.
+    fn point(&self, dimension: u32) -> (u32, u32) {
+        let seed = self.sample.pixel_seed(self.seed, dimension);
+        let index = owen_scramble(self.sample.index, seed as u32);
+        let (x, y) = sobol_2d(index);
+        (
+            owen_scramble(x, (seed >> 32) as u32),
+            owen_scramble(y, super::mix(seed) as u32),
+        )
+    }
+}
+
+impl PixelSampler for SobolSampler {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32, count: u32) {
+        self.sample = SampleIndex::new(pixel, index, count);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.sample.advance(1);
+        fraction(self.point(dimension).0)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.sample.advance(2);
+        let (x, y) = self.point(dimension);
+        (fraction(x), fraction(y))
+    }
+}
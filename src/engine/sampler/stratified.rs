@@ -0,0 +1,68 @@
+use super::{permute, PixelSampler, SampleIndex};
+
+/// Jittered strata: every dimension is split into as many strata as there are samples in the
+/// pixel (a grid for 2D dimensions) and each sample lands in a different one, in an order shuffled
+/// per pixel and dimension. Samples past the pixel count are independent uniform numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StratifiedSampler {
+    pub seed: u64,
+    sample: SampleIndex,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64) -> StratifiedSampler {
+        StratifiedSampler {
+            seed,
+            sample: SampleIndex::default(),
+        }
+    }
+
+    pub fn with_seed(&self, seed: u64) -> StratifiedSampler {
+        StratifiedSampler {
+            seed,
+            ..self.clone()
+        }
+    }
+
+    fn stratum(&self, strata: u32, dimension: u32) -> u32 {
+        let seed = self.sample.pixel_seed(self.seed, dimension) as u32;
+        permute(self.sample.index, strata, seed)
+    }
+}
+
+impl PixelSampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, pixel: (u32, u32), index: u32, count: u32) {
+        self.sample = SampleIndex::new(pixel, index, count);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.sample.advance(1);
+        let jitter = self.sample.random(self.seed, dimension);
+        let count = self.sample.count;
+        if self.sample.index >= count {
+            return jitter;
+        }
+        (self.stratum(count, dimension) as f64 + jitter) / count as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.sample.advance(2);
+        let jitter = (
+            self.sample.random(self.seed, dimension),
+            self.sample.random(self.seed, dimension + 1),
+        );
+        let count = self.sample.count;
+        if self.sample.index >= count {
+            return jitter;
+        }
+
+        // The smallest grid with enough cells, as square as possible
+        let columns = (count as f64).sqrt().ceil() as u32;
+        let rows = count.div_ceil(columns);
+        let cell = self.stratum(columns * rows, dimension);
+        (
+            ((cell % columns) as f64 + jitter.0) / columns as f64,
+            ((cell / columns) as f64 + jitter.1) / rows as f64,
+        )
+    }
+}
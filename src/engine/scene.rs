@@ -1,15 +1,16 @@
-use image::RgbImage;
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use std::{path::Path, str::FromStr};
 
 use crate::{
-    light::Light,
+    light::{environment::EnvironmentLight, Light},
+    loader::{self, error::SceneError},
     objects::{Intersectable, Object},
-    utils::color::Color,
+    utils::{color::Color, math::vector::Vector},
 };
 
-use super::{camera::Camera, intersection::Intersection, ray::Ray};
-
-use indicatif::ParallelProgressIterator;
+use super::{
+    bounding_box::BoundingBox, bvh::Bvh, camera::Camera, film::Film, intersection::Intersection,
+    ray::Ray, renderer::Renderer,
+};
 
 #[derive(Debug, Clone)]
 pub struct Scene {
@@ -17,6 +18,10 @@ pub struct Scene {
     pub objects: Vec<Object>,
     pub lights: Vec<Light>,
     pub background: Color,
+    pub bvh: Bvh,
+    /// Id of every texture of every object, indexed by object then by `Intersection::texture`.
+    /// Equal textures share an id, see `Aov::MaterialId`.
+    pub material_ids: Vec<Vec<u32>>,
 }
 
 impl Scene {
@@ -26,16 +31,81 @@ impl Scene {
         lights: Vec<Light>,
         background: Color,
     ) -> Scene {
+        let bvh = Bvh::new(&Scene::bounding_boxes(&objects));
+        let material_ids = Scene::material_ids(&objects);
         Scene {
             camera,
             objects,
             lights,
             background,
+            bvh,
+            material_ids,
         }
     }
 
+    fn bounding_boxes(objects: &[Object]) -> Vec<BoundingBox> {
+        objects.iter().map(|object| object.bounding_box()).collect()
+    }
+
+    /// Numbers the distinct textures of `objects` in order of appearance.
+    fn material_ids(objects: &[Object]) -> Vec<Vec<u32>> {
+        let mut textures = Vec::new();
+        objects
+            .iter()
+            .map(|object| {
+                object
+                    .textures()
+                    .into_iter()
+                    .map(
+                        |texture| match textures.iter().position(|t| *t == texture) {
+                            Some(id) => id as u32,
+                            None => {
+                                textures.push(texture);
+                                (textures.len() - 1) as u32
+                            }
+                        },
+                    )
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Rebuilds the acceleration structure and the material ids, must be called after mutating
+    /// `objects` directly.
+    pub fn build_bvh(&mut self) {
+        self.bvh = Bvh::new(&Scene::bounding_boxes(&self.objects));
+        self.material_ids = Scene::material_ids(&self.objects);
+    }
+
+    /// Id of the texture hit, see `material_ids`.
+    pub fn material_id(&self, object: usize, intersection: &Intersection) -> Option<u32> {
+        self.material_ids
+            .get(object)?
+            .get(intersection.texture as usize)
+            .copied()
+    }
+
+    /// Loads a scene from a JSON scene file, see `loader::scene` for the format.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        loader::scene::from_source(&source, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Rebuilds the whole BVH, adding objects one at a time is quadratic: use `add_objects`,
+    /// `with_objects` or `Scene::new` for more than a handful.
     pub fn add_object(&mut self, object: Object) {
         self.objects.push(object);
+        self.build_bvh();
+    }
+
+    /// Adds several objects, rebuilding the BVH once.
+    pub fn add_objects(&mut self, objects: impl IntoIterator<Item = Object>) {
+        self.objects.extend(objects);
+        self.build_bvh();
     }
 
     pub fn add_light(&mut self, light: Light) {
@@ -51,6 +121,7 @@ impl Scene {
     pub fn with_objects(&self, objects: Vec<Object>) -> Scene {
         let mut scene = self.clone();
         scene.objects = objects;
+        scene.build_bvh();
         scene
     }
 
@@ -66,6 +137,7 @@ impl Scene {
         scene
     }
 
+    /// Rebuilds the BVH like `add_object`, avoid chaining it.
     pub fn with_object(&self, object: Object) -> Scene {
         let mut scene = self.clone();
         scene.add_object(object);
@@ -78,105 +150,67 @@ impl Scene {
         scene
     }
 
-    pub fn trace(&self, ray: Ray) -> Option<Intersection> {
-        self.objects.iter().fold(None, |iter, object| {
-            let object_inter = object.intersect(&ray);
-            match (iter, object_inter) {
-                (None, None) => None,
-                (Some(inter), None) => Some(inter),
-                (None, Some(inter)) => Some(inter),
-                (Some(inter1), Some(inter2)) => {
-                    if inter1.distance < inter2.distance {
-                        Some(inter1)
-                    } else {
-                        Some(inter2)
-                    }
-                }
-            }
+    /// The first environment light of the scene, if any.
+    pub fn environment(&self) -> Option<&EnvironmentLight> {
+        self.lights.iter().find_map(|light| match light {
+            Light::Environment(environment) => Some(environment),
+            _ => None,
         })
     }
 
-    pub fn render_into(&self, image: &mut RgbImage) {
-        let (width, height) = image.dimensions();
-        let bar = indicatif::ProgressBar::new((width * height).into());
+    /// Color seen by a ray escaping the scene in `direction`, the environment map if there is one.
+    pub fn background_at(&self, direction: &Vector) -> Color {
+        match self.environment() {
+            Some(environment) => environment.radiance(direction),
+            None => self.background,
+        }
+    }
 
-        let mut color_each = self
-            .camera
-            .ray_par_iter((width as usize, height as usize))
-            .progress_with(bar)
-            .map(|(x, y, ray)| {
-                let color = ray.cast(self.clone());
-                (x, y, color)
-            })
-            .collect::<Vec<_>>();
+    pub fn trace(&self, ray: Ray) -> Option<Intersection> {
+        self.bvh
+            .intersect(&ray, |index| self.objects[index].intersect(&ray))
+    }
 
-        let local_contrast = color_each
-            .iter()
-            .map(|(x, y, color)| {
-                let mut surrounding_colors = Vec::new();
-                for x_offset in -1..=1 {
-                    for y_offset in -1..=1 {
-                        if x_offset == 0 && y_offset == 0 {
-                            continue;
-                        }
-                        let x = *x as i32 + x_offset;
-                        let y = *y as i32 + y_offset;
-                        if x < 0 || y < 0 {
-                            continue;
-                        }
-                        let x = x as u32;
-                        let y = y as u32;
-                        if x >= width || y >= height {
-                            continue;
-                        }
-                        surrounding_colors.push(image.get_pixel(x, y));
-                    }
-                }
-
-                let average_color = surrounding_colors
-                    .iter()
-                    .fold(Color::from((0.0, 0.0, 0.0)), |acc, color| {
-                        acc + Into::<Color>::into(**color)
-                    })
-                    / surrounding_colors.len() as f64;
-
-                color.contrast(average_color)
+    /// Like `trace`, with the index of the object hit in `objects`.
+    pub fn trace_object(&self, ray: Ray) -> Option<(usize, Intersection)> {
+        self.bvh
+            .nearest(&ray, |index| {
+                self.objects[index]
+                    .intersect(&ray)
+                    .map(|intersection| (intersection.distance, (index, intersection)))
             })
-            .collect::<Vec<_>>();
-
-        // Apply anti-aliasing
-        if self.camera.anti_aliasing > 1 {
-            let bar = indicatif::ProgressBar::new((width * height).into());
-            color_each = color_each
-                .par_iter()
-                .progress_with(bar)
-                .map(|(x, y, color)| {
-                    let rng = &mut rand::thread_rng();
-                    if local_contrast[(x + y * width as usize)] > 0.5 {
-                        (
-                            *x,
-                            *y,
-                            self.camera
-                                .ray_aa(
-                                    (*x as f64, *y as f64),
-                                    (width as usize, height as usize),
-                                    self.camera.anti_aliasing as usize,
-                                    rng,
-                                )
-                                .iter()
-                                .fold(Color::zero(), |acc, ray| acc + ray.cast(self.clone()))
-                                / self.camera.anti_aliasing as f64,
-                        )
-                    } else {
-                        (*x, *y, *color)
-                    }
-                })
-                .collect::<Vec<_>>();
-        }
+            .map(|(_, hit)| hit)
+    }
 
-        for (x, y, color) in color_each {
-            image.put_pixel(x as u32, y as u32, color.into());
-        }
+    /// Whether something blocks `ray` before it travels `max_distance`.
+    pub fn occluded(&self, ray: Ray, max_distance: f64) -> bool {
+        self.bvh.any_hit(&ray, max_distance, |index| {
+            self.objects[index].hits(&ray, max_distance)
+        })
+    }
+
+    /// Renders the scene into a new film of the given size.
+    pub fn render(&self, width: u32, height: u32) -> Film {
+        Renderer::new(self).render(width, height)
+    }
+
+    /// Renders with the default `Renderer` settings, see `Renderer` for progress reporting and
+    /// cancellation.
+    ///
+    /// Every pixel draws its samples from its own stream of the camera sampler, so the image only
+    /// depends on the scene and the sampler seed, not on the number of threads. Samples are
+    /// splatted through the camera filter. With adaptive sampling, passes are rendered until every
+    /// pixel is below the noise threshold or at the maximum sample count.
+    pub fn render_into(&self, film: &mut Film) {
+        Renderer::new(self).render_into(film);
+    }
+}
+
+impl FromStr for Scene {
+    type Err = SceneError;
+
+    fn from_str(source: &str) -> Result<Scene, SceneError> {
+        loader::scene::from_str(source)
     }
 }
 
@@ -187,6 +221,46 @@ impl Default for Scene {
             objects: Vec::new(),
             lights: Vec::new(),
             background: Color::from((0.0, 0.0, 0.0)),
+            bvh: Bvh::default(),
+            material_ids: Vec::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r##"{
+        "camera": {
+            "position": [-6, 2, 0], "direction": [1, -0.3, 0], "anti_aliasing": 4,
+            "integrator": "path_tracing", "sampler": "random", "seed": 3
+        },
+        "objects": [
+            { "type": "plane", "position": [0, -1, 0], "normal": [0, 1, 0] },
+            { "type": "sphere", "center": [0, 0, -1.5], "radius": 1 },
+            { "type": "sphere", "center": [0, 0, 1.5], "radius": 1 }
+        ],
+        "lights": [
+            { "type": "rect", "position": [0, 4, -1.5], "u": [2, 0, 0], "v": [0, 0, 2], "intensity": 6 }
+        ]
+    }"##;
+
+    fn render(scene: &Scene, threads: usize) -> Film {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| scene.render(24, 16))
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_image_on_any_thread_count() {
+        let scene = SCENE.parse::<Scene>().unwrap();
+        let single = render(&scene, 1);
+        assert_eq!(single.pixels, render(&scene, 4).pixels);
+
+        let camera = scene.camera.with_sampler(scene.camera.sampler.with_seed(4));
+        assert_ne!(single.pixels, render(&scene.with_camera(camera), 4).pixels);
+    }
+}
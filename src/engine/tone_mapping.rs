@@ -0,0 +1,166 @@
+use crate::utils::color::Color;
+
+use super::film::Film;
+
+/// Curve compressing linear radiance into the displayable [0, 1] range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Values above the white point are clipped.
+    #[default]
+    Clamp,
+    /// `L / (1 + L)` on the luminance, never reaches white.
+    Reinhard,
+    /// Reinhard with the white point mapped to 1, the brightest pixel by default.
+    ExtendedReinhard,
+    /// Narkowicz's fit of the ACES filmic curve, per channel.
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2, per channel.
+    Uncharted2,
+}
+
+impl ToneMapping {
+    fn default_white_point(&self, film_white: f64) -> f64 {
+        match self {
+            ToneMapping::Clamp => 1.0,
+            ToneMapping::Reinhard | ToneMapping::Aces => f64::INFINITY,
+            ToneMapping::ExtendedReinhard => film_white,
+            ToneMapping::Uncharted2 => 11.2,
+        }
+    }
+
+    fn map(&self, color: Color, white: f64) -> Color {
+        match self {
+            ToneMapping::Clamp => color / white,
+            ToneMapping::Reinhard | ToneMapping::ExtendedReinhard => {
+                let luminance = color.relative_luminance();
+                if luminance <= 0.0 {
+                    return Color::zero();
+                }
+                let mapped = luminance * (1.0 + luminance / (white * white)) / (1.0 + luminance);
+                color * (mapped / luminance)
+            }
+            ToneMapping::Aces if white.is_infinite() => per_channel(color, aces),
+            ToneMapping::Aces => per_channel(color, |x| aces(x) / aces(white)),
+            ToneMapping::Uncharted2 => per_channel(color, |x| uncharted2(x) / uncharted2(white)),
+        }
+    }
+}
+
+fn per_channel(color: Color, curve: impl Fn(f64) -> f64) -> Color {
+    Color::new(
+        curve(color.r.max(0.0)),
+        curve(color.g.max(0.0)),
+        curve(color.b.max(0.0)),
+    )
+}
+
+fn aces(x: f64) -> f64 {
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+fn uncharted2(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+/// Exposure and tone mapping applied when a film is converted to 8-bit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapper {
+    pub operator: ToneMapping,
+    /// Exposure compensation in stops, every unit doubles the brightness.
+    pub exposure: f64,
+    /// Scales the image so that its log-average luminance becomes middle grey before `exposure`
+    /// is applied.
+    pub auto_exposure: bool,
+    /// Exposed luminance mapped to white, each operator has its own default.
+    pub white_point: Option<f64>,
+}
+
+impl ToneMapper {
+    /// Luminance the log-average is mapped to by auto exposure.
+    pub const MIDDLE_GREY: f64 = 0.18;
+
+    pub fn new(operator: ToneMapping) -> ToneMapper {
+        ToneMapper {
+            operator,
+            ..ToneMapper::default()
+        }
+    }
+
+    pub fn with_operator(&self, operator: ToneMapping) -> ToneMapper {
+        ToneMapper { operator, ..*self }
+    }
+
+    pub fn with_exposure(&self, exposure: f64) -> ToneMapper {
+        ToneMapper { exposure, ..*self }
+    }
+
+    pub fn with_auto_exposure(&self, auto_exposure: bool) -> ToneMapper {
+        ToneMapper {
+            auto_exposure,
+            ..*self
+        }
+    }
+
+    pub fn with_white_point(&self, white_point: f64) -> ToneMapper {
+        ToneMapper {
+            white_point: Some(white_point),
+            ..*self
+        }
+    }
+
+    /// Multiplier applied to the film before the curve.
+    pub fn scale(&self, film: &Film) -> f64 {
+        let scale = 2.0_f64.powf(self.exposure);
+        if self.auto_exposure {
+            let average = log_average_luminance(film);
+            if average > 0.0 {
+                return scale * ToneMapper::MIDDLE_GREY / average;
+            }
+        }
+        scale
+    }
+
+    /// Display referred colors of the film, in [0, 1] for every operator but `Clamp`.
+    pub fn apply(&self, film: &Film) -> Vec<Color> {
+        let scale = self.scale(film);
+        let white = self.white_point.unwrap_or_else(|| {
+            let brightest = film
+                .pixels
+                .iter()
+                .map(|color| color.relative_luminance())
+                .fold(0.0, f64::max);
+            self.operator.default_white_point(brightest * scale)
+        });
+        let white = if white > 0.0 { white } else { 1.0 };
+
+        film.pixels
+            .iter()
+            .map(|color| self.operator.map(*color * scale, white))
+            .collect()
+    }
+}
+
+impl Default for ToneMapper {
+    fn default() -> ToneMapper {
+        ToneMapper {
+            operator: ToneMapping::default(),
+            exposure: 0.0,
+            auto_exposure: false,
+            white_point: None,
+        }
+    }
+}
+
+/// Geometric mean of the luminance, the small offset keeps black pixels from dominating it.
+pub fn log_average_luminance(film: &Film) -> f64 {
+    if film.pixels.is_empty() {
+        return 0.0;
+    }
+    let sum = film
+        .pixels
+        .iter()
+        .map(|color| (1e-4 + color.relative_luminance().max(0.0)).ln())
+        .sum::<f64>();
+    (sum / film.pixels.len() as f64).exp()
+}
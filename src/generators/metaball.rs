@@ -1,7 +1,10 @@
+use std::collections::HashMap;
+
 use crate::{
     material::texture::Texture,
     objects::{
-        mesh::Mesh, smooth_triangle::SmoothTriangle, triangle::Triangle, Intersectable, Object,
+        mesh::{Face, Mesh},
+        Object,
     },
     utils::math::{point::Point, vector::Vector},
 };
@@ -129,45 +132,45 @@ impl Metaball {
         self.points.iter().fold(0.0, |acc, p| acc + p.value(point))
     }
 
-    fn iter_points(&self) -> impl Iterator<Item = Point> + '_ {
-        let nb_cells_x = ((self.bounds.1.x - self.bounds.0.x) / self.resolution).ceil() as usize;
-        let nb_cells_y = ((self.bounds.1.y - self.bounds.0.y) / self.resolution).ceil() as usize;
-        let nb_cells_z = ((self.bounds.1.z - self.bounds.0.z) / self.resolution).ceil() as usize;
-
-        (0..nb_cells_x)
-            .flat_map(move |x| {
-                (0..nb_cells_y).flat_map(move |y| (0..nb_cells_z).map(move |z| (x, y, z)))
-            })
-            .map(move |(x, y, z)| {
-                Point::new(
-                    self.bounds.0.x + x as f64 * self.resolution,
-                    self.bounds.0.y + y as f64 * self.resolution,
-                    self.bounds.0.z + z as f64 * self.resolution,
-                )
-            })
+    fn cell_counts(&self) -> [usize; 3] {
+        [
+            ((self.bounds.1.x - self.bounds.0.x) / self.resolution).ceil() as usize,
+            ((self.bounds.1.y - self.bounds.0.y) / self.resolution).ceil() as usize,
+            ((self.bounds.1.z - self.bounds.0.z) / self.resolution).ceil() as usize,
+        ]
     }
 
-    fn cube_at(&self, point: &Point) -> Vec<Point> {
-        [0, 1, 3, 2, 4, 5, 7, 6]
-            .iter()
-            .map(|i| {
-                *point
-                    + Point::new(
-                        (i & 1) as f64 * self.resolution,
-                        ((i >> 1) & 1) as f64 * self.resolution,
-                        ((i >> 2) & 1) as f64 * self.resolution,
-                    )
-            })
-            .collect()
+    /// Position of a corner of the grid, corners are addressed by integer coordinates so that
+    /// neighbouring cubes share them exactly.
+    fn grid_point(&self, corner: [usize; 3]) -> Point {
+        Point::new(
+            self.bounds.0.x + corner[0] as f64 * self.resolution,
+            self.bounds.0.y + corner[1] as f64 * self.resolution,
+            self.bounds.0.z + corner[2] as f64 * self.resolution,
+        )
     }
 
-    fn iter_cubes(&self) -> impl Iterator<Item = Vec<Point>> + '_ {
-        self.iter_points().map(move |p1| self.cube_at(&p1))
+    fn iter_cells(&self) -> impl Iterator<Item = [usize; 3]> {
+        let [nb_cells_x, nb_cells_y, nb_cells_z] = self.cell_counts();
+
+        (0..nb_cells_x).flat_map(move |x| {
+            (0..nb_cells_y).flat_map(move |y| (0..nb_cells_z).map(move |z| [x, y, z]))
+        })
     }
 
-    fn cube_value(&self, cube: &[Point]) -> usize {
-        cube.iter().fold(0, |acc, p| {
-            acc * 2 + (self.value(p) < self.fronteer) as usize
+    fn cube_at(&self, cell: [usize; 3]) -> [[usize; 3]; 8] {
+        [0, 1, 3, 2, 4, 5, 7, 6].map(|i| {
+            [
+                cell[0] + (i & 1),
+                cell[1] + ((i >> 1) & 1),
+                cell[2] + ((i >> 2) & 1),
+            ]
+        })
+    }
+
+    fn cube_value(&self, cube: &[[usize; 3]]) -> usize {
+        cube.iter().fold(0, |acc, corner| {
+            acc * 2 + (self.value(&self.grid_point(*corner)) < self.fronteer) as usize
         })
     }
 }
@@ -189,69 +192,71 @@ impl Default for Metaball {
 
 impl MeshGenerator for Metaball {
     fn generate(&self) -> Mesh {
-        let points = self.iter_cubes().fold(Vec::new(), |points, cube| {
+        let mut positions = Vec::new();
+        let mut faces = Vec::new();
+        // Vertices lie on the edges of the grid, an edge is identified by its two corners
+        let mut vertices = HashMap::<([usize; 3], [usize; 3]), u32>::new();
+
+        for cell in self.iter_cells() {
+            let cube = self.cube_at(cell);
             let cube_value = self.cube_value(&cube);
 
             if cube_value == 0 || cube_value == 255 {
-                return points;
+                continue;
             }
 
             let config = TRI_TABLE[cube_value];
 
-            let mut points = points;
             for i in 0..5 {
-                let edge = config[i * 3];
-                if edge == -1 {
+                if config[i * 3] == -1 {
                     break;
                 }
-                (0..3).for_each(|j| {
-                    let edge = config[i * 3 + j];
-                    let edge = EDGE_TABLE[edge as usize];
-                    let p1 = cube[7 - edge[0]];
-                    let p2 = cube[7 - edge[1]];
-                    let value1 = self.value(&p1);
-                    let value2 = self.value(&p2);
-                    let t = (self.fronteer - value1) / (value2 - value1);
-                    let p = p1 + (p2 - p1) * t;
-                    points.push(p);
+                let triangle = [0, 1, 2].map(|j| {
+                    let edge = EDGE_TABLE[config[i * 3 + j] as usize];
+                    let c1 = cube[7 - edge[0]];
+                    let c2 = cube[7 - edge[1]];
+                    let key = if c1 < c2 { (c1, c2) } else { (c2, c1) };
+                    *vertices.entry(key).or_insert_with(|| {
+                        let p1 = self.grid_point(key.0);
+                        let p2 = self.grid_point(key.1);
+                        let value1 = self.value(&p1);
+                        let value2 = self.value(&p2);
+                        let t = (self.fronteer - value1) / (value2 - value1);
+                        positions.push(p1 + (p2 - p1) * t);
+                        (positions.len() - 1) as u32
+                    })
                 });
+
+                // Skip the triangles collapsed by vertices sitting on a corner
+                if triangle[0] != triangle[1]
+                    && triangle[1] != triangle[2]
+                    && triangle[2] != triangle[0]
+                {
+                    faces.push(Face::new(triangle).with_normals(triangle));
+                }
             }
+        }
 
-            points
-        });
-
-        let mesh = Mesh::default();
-
-        // Compute all the normal triangles
-        let tri = (0..points.len())
-            .step_by(3)
-            .map(|i| {
-                Triangle::default()
-                    .with_a(points[i])
-                    .with_b(points[i + 1])
-                    .with_c(points[i + 2])
-            })
-            .collect::<Vec<_>>();
-
-        let smooth_tri = tri.iter().map(|t| {
-            let normals = t
-                .points
-                .iter()
-                .map(|p| {
-                    tri.iter()
-                        .filter(|t| t.points.iter().any(|p2| (*p - *p2).length() < 1e-6))
-                        .fold(Vector::zero(), |acc, t| acc + t.normal(p))
-                        .normalize()
-                })
-                .collect();
-            SmoothTriangle::default()
-                .with_points(t.points)
-                .with_normals(normals)
-                .with_texture(self.texture)
-        });
-
-        //mesh.with_faces(tri.iter().map(|t| t.clone().into()).collect())
-        mesh.with_faces(smooth_tri.map(|t| t.into()).collect())
+        // Vertex normals are the average of the normals of the adjacent faces
+        let mut normals = vec![Vector::zero(); positions.len()];
+        for face in faces.iter() {
+            let [a, b, c] = face.positions.map(|i| positions[i as usize]);
+            let normal = (b - a).cross(&(c - a));
+            if normal.length_squared() > 0.0 {
+                for i in face.positions {
+                    normals[i as usize] += normal.normalize();
+                }
+            }
+        }
+        let normals = normals.iter().map(|normal| normal.normalize()).collect();
+
+        Mesh::new(
+            positions,
+            normals,
+            Vec::new(),
+            faces,
+            vec![self.texture.clone()],
+        )
     }
 }
 
@@ -1,6 +1,7 @@
 pub mod engine;
 pub mod generators;
 pub mod light;
+pub mod loader;
 pub mod material;
 pub mod objects;
 pub mod utils;
@@ -3,13 +3,16 @@ use crate::utils::{
     math::{point::Point, vector::Vector},
 };
 
-use super::{Emittable, Light};
+use super::{Emittable, Light, LightSample};
 
 #[derive(Debug, Clone)]
 pub struct DirectionalLight {
     pub direction: Vector,
     pub color: Color,
     pub intensity: f64,
+    /// Apparent size of the light in degrees, 0 gives hard shadows. The sun is about 0.53°.
+    pub angular_diameter: f64,
+    pub samples: u32,
 }
 
 impl DirectionalLight {
@@ -18,6 +21,8 @@ impl DirectionalLight {
             direction,
             color,
             intensity,
+            angular_diameter: 0.0,
+            samples: 16,
         }
     }
 
@@ -38,6 +43,18 @@ impl DirectionalLight {
         light.intensity = intensity;
         light
     }
+
+    pub fn with_angular_diameter(&self, angular_diameter: f64) -> DirectionalLight {
+        let mut light = self.clone();
+        light.angular_diameter = angular_diameter;
+        light
+    }
+
+    pub fn with_samples(&self, samples: u32) -> DirectionalLight {
+        let mut light = self.clone();
+        light.samples = samples;
+        light
+    }
 }
 
 impl Emittable for DirectionalLight {
@@ -52,6 +69,32 @@ impl Emittable for DirectionalLight {
     fn distance(&self, _point: Point) -> f64 {
         f64::INFINITY
     }
+
+    fn samples(&self) -> u32 {
+        if self.angular_diameter > 0.0 {
+            self.samples.max(1)
+        } else {
+            1
+        }
+    }
+
+    fn is_delta(&self) -> bool {
+        self.angular_diameter <= 0.0
+    }
+
+    fn sample(&self, point: Point, u: f64, v: f64) -> LightSample {
+        let direction = if self.angular_diameter > 0.0 {
+            let cos_max = (self.angular_diameter.to_radians() / 2.0).cos();
+            self.direction.normalize().uniform_cone(cos_max, u, v)
+        } else {
+            self.direction
+        };
+        LightSample {
+            direction,
+            distance: f64::INFINITY,
+            intensity: self.intensity(point),
+        }
+    }
 }
 
 impl From<DirectionalLight> for Light {
@@ -66,6 +109,8 @@ impl Default for DirectionalLight {
             direction: Vector::new(0.0, 0.0, -1.0),
             color: Color::from((1.0, 1.0, 1.0)),
             intensity: 1.0,
+            angular_diameter: 0.0,
+            samples: 16,
         }
     }
 }
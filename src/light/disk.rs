@@ -0,0 +1,132 @@
+use crate::utils::{
+    color::Color,
+    math::{concentric_disk, point::Point, vector::Vector},
+};
+
+use super::{Emittable, Light, LightSample};
+
+/// Disk light emitting on the side of its normal with a cosine falloff.
+#[derive(Debug, Clone)]
+pub struct DiskLight {
+    pub position: Point,
+    pub normal: Vector,
+    pub radius: f64,
+    pub color: Color,
+    pub intensity: f64,
+    pub samples: u32,
+}
+
+impl DiskLight {
+    pub fn new(
+        position: Point,
+        normal: Vector,
+        radius: f64,
+        color: Color,
+        intensity: f64,
+        samples: u32,
+    ) -> DiskLight {
+        DiskLight {
+            position,
+            normal,
+            radius,
+            color,
+            intensity,
+            samples,
+        }
+    }
+
+    pub fn with_position(&self, position: Point) -> DiskLight {
+        let mut light = self.clone();
+        light.position = position;
+        light
+    }
+
+    pub fn with_normal(&self, normal: Vector) -> DiskLight {
+        let mut light = self.clone();
+        light.normal = normal;
+        light
+    }
+
+    pub fn with_radius(&self, radius: f64) -> DiskLight {
+        let mut light = self.clone();
+        light.radius = radius;
+        light
+    }
+
+    pub fn with_color(&self, color: Color) -> DiskLight {
+        let mut light = self.clone();
+        light.color = color;
+        light
+    }
+
+    pub fn with_intensity(&self, intensity: f64) -> DiskLight {
+        let mut light = self.clone();
+        light.intensity = intensity;
+        light
+    }
+
+    pub fn with_samples(&self, samples: u32) -> DiskLight {
+        let mut light = self.clone();
+        light.samples = samples;
+        light
+    }
+
+    fn emitted(&self, from: Point, to: Point) -> Color {
+        let distance = from.distance(&to);
+        let cos_light = self.normal.dot(&(from - to).normalize()).max(0.0);
+        self.color * (self.intensity / distance * cos_light)
+    }
+}
+
+impl Default for DiskLight {
+    fn default() -> Self {
+        DiskLight {
+            position: Point::new(0.0, 0.0, 0.0),
+            normal: Vector::new(0.0, 0.0, -1.0),
+            radius: 1.0,
+            color: "#FFFFFF".into(),
+            intensity: 10.0,
+            samples: 16,
+        }
+    }
+}
+
+impl Emittable for DiskLight {
+    fn intensity(&self, point: Point) -> Color {
+        self.emitted(point, self.position)
+    }
+
+    fn direction(&self, point: Point) -> Vector {
+        (self.position - point).normalize()
+    }
+
+    fn distance(&self, point: Point) -> f64 {
+        self.position.distance(&point)
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples.max(1)
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, point: Point, u: f64, v: f64) -> LightSample {
+        let (x, y) = concentric_disk(u, v);
+        let (tangent, bitangent) = self.normal.orthonormal_basis();
+        let target = self.position + tangent * (self.radius * x) + bitangent * (self.radius * y);
+
+        LightSample {
+            direction: (target - point).normalize(),
+            distance: target.distance(&point),
+            intensity: self.emitted(point, target),
+        }
+    }
+}
+
+impl From<DiskLight> for Light {
+    fn from(disk: DiskLight) -> Light {
+        Light::Disk(disk)
+    }
+}
@@ -0,0 +1,335 @@
+use std::{f64::consts::PI, fmt, fs::File, io::BufReader, path::Path, sync::Arc};
+
+use image::{codecs::hdr::HdrDecoder, DynamicImage, ImageError, ImageFormat};
+
+use crate::{
+    engine::checkpoint::checksum,
+    utils::{
+        color::Color,
+        math::{point::Point, vector::Vector},
+    },
+};
+
+use super::{Emittable, Light, LightSample};
+
+/// Equirectangular radiance map, along with the distribution used to importance sample it.
+#[derive(Clone)]
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    /// Linear radiance, row by row from the top of the image.
+    pub pixels: Vec<Color>,
+    /// Cumulative weights of the rows, normalized to end at 1.
+    rows: Vec<f64>,
+    /// Cumulative weights of the texels of each row, normalized to end at 1.
+    columns: Vec<Vec<f64>>,
+    /// Sum of the weights of every texel.
+    total: f64,
+    /// Hash of the pixels, see `checksum`.
+    checksum: u64,
+}
+
+impl EnvironmentMap {
+    /// Texels are weighted by their luminance and the solid angle they cover, so bright spots such
+    /// as the sun are sampled far more often than the rest of the sky.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> EnvironmentMap {
+        assert_eq!(pixels.len(), width * height, "pixel count mismatch");
+
+        let weights = pixels
+            .chunks(width)
+            .enumerate()
+            .map(|(row, texels)| {
+                let sin_theta = ((row as f64 + 0.5) / height as f64 * PI).sin();
+                texels
+                    .iter()
+                    .map(|texel| texel.relative_luminance().max(0.0) * sin_theta)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let row_sums = weights
+            .iter()
+            .map(|row| row.iter().sum::<f64>())
+            .collect::<Vec<_>>();
+        let total = row_sums.iter().sum::<f64>();
+
+        EnvironmentMap {
+            width,
+            height,
+            checksum: checksum(&pixels),
+            pixels,
+            rows: cumulative(&row_sums),
+            columns: weights.iter().map(|row| cumulative(row)).collect(),
+            total,
+        }
+    }
+
+    /// Loads a panorama, HDR and EXR files are linear while other formats are gamma decoded.
+    pub fn load(path: impl AsRef<Path>) -> Result<EnvironmentMap, ImageError> {
+        let path = path.as_ref();
+        // The generic decoder converts Radiance files to 8-bit
+        if ImageFormat::from_path(path).ok() == Some(ImageFormat::Hdr) {
+            let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+            let (width, height) = (
+                decoder.metadata().width as usize,
+                decoder.metadata().height as usize,
+            );
+            let pixels = decoder
+                .read_image_hdr()?
+                .into_iter()
+                .map(|pixel| Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64))
+                .collect();
+            return Ok(EnvironmentMap::new(width, height, pixels));
+        }
+
+        let image = image::open(path)?;
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => image
+                .into_rgb32f()
+                .pixels()
+                .map(|pixel| Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64))
+                .collect(),
+            _ => image
+                .into_rgb8()
+                .pixels()
+                .map(|pixel| Color::from(*pixel))
+                .collect(),
+        };
+        Ok(EnvironmentMap::new(width, height, pixels))
+    }
+
+    /// Radiance of the texel containing the image coordinates `u` and `v` in [0, 1].
+    pub fn lookup(&self, u: f64, v: f64) -> Color {
+        let (column, row) = self.texel(u, v);
+        self.pixels[row * self.width + column]
+    }
+
+    /// Mean radiance over the sphere.
+    pub fn average(&self) -> Color {
+        let mut sum = Color::zero();
+        let mut weight = 0.0;
+        for (row, texels) in self.pixels.chunks(self.width).enumerate() {
+            let sin_theta = ((row as f64 + 0.5) / self.height as f64 * PI).sin();
+            for texel in texels {
+                sum = sum + *texel * sin_theta;
+                weight += sin_theta;
+            }
+        }
+        if weight > 0.0 {
+            sum / weight
+        } else {
+            Color::zero()
+        }
+    }
+
+    fn texel(&self, u: f64, v: f64) -> (usize, usize) {
+        let column = ((u * self.width as f64) as usize).min(self.width - 1);
+        let row = ((v * self.height as f64) as usize).min(self.height - 1);
+        (column, row)
+    }
+
+    /// Picks image coordinates proportionally to the weights, returns them with their density over
+    /// the unit square. The density is 0 for a black map.
+    fn sample(&self, u: f64, v: f64) -> (f64, f64, f64) {
+        if self.total <= 0.0 {
+            return (u, v, 0.0);
+        }
+        let (row, y) = sample_cumulative(&self.rows, u);
+        let (column, x) = sample_cumulative(&self.columns[row], v);
+        let (u, v) = (
+            (column as f64 + x) / self.width as f64,
+            (row as f64 + y) / self.height as f64,
+        );
+        (u, v, self.density(column, row))
+    }
+
+    fn density(&self, column: usize, row: usize) -> f64 {
+        let sin_theta = ((row as f64 + 0.5) / self.height as f64 * PI).sin();
+        let weight = self.pixels[row * self.width + column]
+            .relative_luminance()
+            .max(0.0)
+            * sin_theta;
+        weight * (self.width * self.height) as f64 / self.total
+    }
+}
+
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("checksum", &self.checksum)
+            .finish_non_exhaustive()
+    }
+}
+
+fn cumulative(weights: &[f64]) -> Vec<f64> {
+    let total = weights.iter().sum::<f64>();
+    let mut sum = 0.0;
+    let mut cdf = Vec::with_capacity(weights.len() + 1);
+    cdf.push(0.0);
+    for (i, weight) in weights.iter().enumerate() {
+        sum += if total > 0.0 {
+            weight / total
+        } else {
+            1.0 / weights.len() as f64
+        };
+        cdf.push(if i + 1 == weights.len() { 1.0 } else { sum });
+    }
+    cdf
+}
+
+/// Returns the bucket of `cdf` containing `u` and the position of `u` inside of it.
+fn sample_cumulative(cdf: &[f64], u: f64) -> (usize, f64) {
+    let index = cdf
+        .partition_point(|&value| value <= u)
+        .saturating_sub(1)
+        .min(cdf.len() - 2);
+    let width = cdf[index + 1] - cdf[index];
+    let offset = if width > 0.0 {
+        ((u - cdf[index]) / width).clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    (index, offset)
+}
+
+/// Light coming from an environment map surrounding the scene, also seen as the background.
+///
+/// The center of the image faces the local `x` axis when `rotation` is 0, the top row faces `up`.
+#[derive(Debug, Clone)]
+pub struct EnvironmentLight {
+    pub map: Arc<EnvironmentMap>,
+    pub intensity: f64,
+    /// Rotation around `up`, in degrees.
+    pub rotation: f64,
+    pub up: Vector,
+    pub samples: u32,
+}
+
+impl EnvironmentLight {
+    pub fn new(map: Arc<EnvironmentMap>, intensity: f64, rotation: f64) -> EnvironmentLight {
+        EnvironmentLight {
+            map,
+            intensity,
+            rotation,
+            up: Vector::new(0.0, 1.0, 0.0),
+            samples: 16,
+        }
+    }
+
+    pub fn with_map(&self, map: Arc<EnvironmentMap>) -> EnvironmentLight {
+        let mut light = self.clone();
+        light.map = map;
+        light
+    }
+
+    pub fn with_intensity(&self, intensity: f64) -> EnvironmentLight {
+        let mut light = self.clone();
+        light.intensity = intensity;
+        light
+    }
+
+    pub fn with_rotation(&self, rotation: f64) -> EnvironmentLight {
+        let mut light = self.clone();
+        light.rotation = rotation;
+        light
+    }
+
+    pub fn with_up(&self, up: Vector) -> EnvironmentLight {
+        let mut light = self.clone();
+        light.up = up;
+        light
+    }
+
+    pub fn with_samples(&self, samples: u32) -> EnvironmentLight {
+        let mut light = self.clone();
+        light.samples = samples;
+        light
+    }
+
+    /// Radiance coming from `direction`.
+    pub fn radiance(&self, direction: &Vector) -> Color {
+        let (u, v) = self.image_coordinates(direction);
+        self.map.lookup(u, v) * self.intensity
+    }
+
+    /// Orthonormal frame `(x, up, z)` of the map.
+    fn frame(&self) -> (Vector, Vector, Vector) {
+        let up = self.up.normalize();
+        let reference = if up.x.abs() > 0.9 {
+            Vector::new(0.0, 0.0, 1.0)
+        } else {
+            Vector::new(1.0, 0.0, 0.0)
+        };
+        let x = (reference - up * reference.dot(&up)).normalize();
+        let z = x.cross(&up);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        (x * cos + z * sin, up, z * cos - x * sin)
+    }
+
+    fn image_coordinates(&self, direction: &Vector) -> (f64, f64) {
+        let (x, y, z) = self.frame();
+        let direction = direction.normalize();
+        let phi = direction.dot(&z).atan2(direction.dot(&x));
+        let theta = direction.dot(&y).clamp(-1.0, 1.0).acos();
+        ((0.5 + phi / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+    }
+
+    fn image_direction(&self, u: f64, v: f64) -> Vector {
+        let (x, y, z) = self.frame();
+        let phi = (u - 0.5) * 2.0 * PI;
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        x * (sin_theta * phi.cos()) + y * cos_theta + z * (sin_theta * phi.sin())
+    }
+}
+
+impl Emittable for EnvironmentLight {
+    fn intensity(&self, _point: Point) -> Color {
+        self.map.average() * self.intensity
+    }
+
+    fn direction(&self, _point: Point) -> Vector {
+        self.up.normalize()
+    }
+
+    fn distance(&self, _point: Point) -> f64 {
+        f64::INFINITY
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples.max(1)
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, _point: Point, u: f64, v: f64) -> LightSample {
+        let (image_u, image_v, density) = self.map.sample(u, v);
+        let direction = self.image_direction(image_u, image_v);
+        let sin_theta = (image_v * PI).sin();
+
+        // Intensities are scaled like the other lights, so that a diffuse surface under a uniform
+        // map reflects `albedo * radiance`: the estimator is L / (pi * pdf) with the pdf over the
+        // sphere being density / (2 pi^2 sin(theta))
+        let intensity = if density > 0.0 && sin_theta > 0.0 {
+            self.map.lookup(image_u, image_v) * (self.intensity * 2.0 * PI * sin_theta / density)
+        } else {
+            Color::zero()
+        };
+
+        LightSample {
+            direction,
+            distance: f64::INFINITY,
+            intensity,
+        }
+    }
+}
+
+impl From<EnvironmentLight> for Light {
+    fn from(environment: EnvironmentLight) -> Light {
+        Light::Environment(environment)
+    }
+}
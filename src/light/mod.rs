@@ -3,16 +3,53 @@ use crate::utils::{
     math::{point::Point, vector::Vector},
 };
 
-use self::{directional::DirectionalLight, point::PointLight, spot::SpotLight};
+use self::{
+    directional::DirectionalLight, disk::DiskLight, environment::EnvironmentLight,
+    point::PointLight, rect::RectLight, sphere::SphereLight, spot::SpotLight,
+};
 
 pub trait Emittable {
     fn intensity(&self, point: Point) -> Color;
     fn direction(&self, point: Point) -> Vector;
     fn distance(&self, point: Point) -> f64;
+
+    /// Number of shadow rays used to estimate the light at a point, 1 for delta lights.
+    fn samples(&self) -> u32 {
+        1
+    }
+
+    /// Whether the light is a single point or direction, `sample` ignores `u` and `v` then.
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    /// Picks a point of the light as seen from `point`, `u` and `v` are uniform in [0, 1).
+    /// Averaging `samples()` of them gives the light received at `point`.
+    fn sample(&self, point: Point, _u: f64, _v: f64) -> LightSample {
+        LightSample {
+            direction: self.direction(point),
+            distance: self.distance(point),
+            intensity: self.intensity(point),
+        }
+    }
+}
+
+/// A point on a light, seen from a shading point.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Normalized direction towards the light.
+    pub direction: Vector,
+    /// Distance to the sampled point, used to bound shadow rays.
+    pub distance: f64,
+    pub intensity: Color,
 }
 
 pub mod directional;
+pub mod disk;
+pub mod environment;
 pub mod point;
+pub mod rect;
+pub mod sphere;
 pub mod spot;
 
 #[derive(Debug, Clone)]
@@ -20,6 +57,10 @@ pub enum Light {
     Directional(DirectionalLight),
     Point(PointLight),
     Spot(SpotLight),
+    Rect(RectLight),
+    Disk(DiskLight),
+    Sphere(SphereLight),
+    Environment(EnvironmentLight),
 }
 
 impl Emittable for Light {
@@ -28,6 +69,10 @@ impl Emittable for Light {
             Light::Directional(directional) => directional.intensity(point),
             Light::Point(point_light) => point_light.intensity(point),
             Light::Spot(spot) => spot.intensity(point),
+            Light::Rect(rect) => rect.intensity(point),
+            Light::Disk(disk) => disk.intensity(point),
+            Light::Sphere(sphere) => sphere.intensity(point),
+            Light::Environment(environment) => environment.intensity(point),
         }
     }
 
@@ -36,6 +81,10 @@ impl Emittable for Light {
             Light::Directional(directional) => directional.direction(point),
             Light::Point(point_light) => point_light.direction(point),
             Light::Spot(spot) => spot.direction(point),
+            Light::Rect(rect) => rect.direction(point),
+            Light::Disk(disk) => disk.direction(point),
+            Light::Sphere(sphere) => sphere.direction(point),
+            Light::Environment(environment) => environment.direction(point),
         }
     }
 
@@ -44,6 +93,46 @@ impl Emittable for Light {
             Light::Directional(directional) => directional.distance(point),
             Light::Point(point_light) => point_light.distance(point),
             Light::Spot(spot) => spot.distance(point),
+            Light::Rect(rect) => rect.distance(point),
+            Light::Disk(disk) => disk.distance(point),
+            Light::Sphere(sphere) => sphere.distance(point),
+            Light::Environment(environment) => environment.distance(point),
+        }
+    }
+
+    fn samples(&self) -> u32 {
+        match self {
+            Light::Directional(directional) => directional.samples(),
+            Light::Point(point_light) => point_light.samples(),
+            Light::Spot(spot) => spot.samples(),
+            Light::Rect(rect) => rect.samples(),
+            Light::Disk(disk) => disk.samples(),
+            Light::Sphere(sphere) => sphere.samples(),
+            Light::Environment(environment) => environment.samples(),
+        }
+    }
+
+    fn is_delta(&self) -> bool {
+        match self {
+            Light::Directional(directional) => directional.is_delta(),
+            Light::Point(point_light) => point_light.is_delta(),
+            Light::Spot(spot) => spot.is_delta(),
+            Light::Rect(rect) => rect.is_delta(),
+            Light::Disk(disk) => disk.is_delta(),
+            Light::Sphere(sphere) => sphere.is_delta(),
+            Light::Environment(environment) => environment.is_delta(),
+        }
+    }
+
+    fn sample(&self, point: Point, u: f64, v: f64) -> LightSample {
+        match self {
+            Light::Directional(directional) => directional.sample(point, u, v),
+            Light::Point(point_light) => point_light.sample(point, u, v),
+            Light::Spot(spot) => spot.sample(point, u, v),
+            Light::Rect(rect) => rect.sample(point, u, v),
+            Light::Disk(disk) => disk.sample(point, u, v),
+            Light::Sphere(sphere) => sphere.sample(point, u, v),
+            Light::Environment(environment) => environment.sample(point, u, v),
         }
     }
 }
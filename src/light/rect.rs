@@ -0,0 +1,129 @@
+use crate::utils::{
+    color::Color,
+    math::{point::Point, vector::Vector},
+};
+
+use super::{Emittable, Light, LightSample};
+
+/// Parallelogram light centered on `position` with edges `u` and `v`, it emits on the side of
+/// `u × v` with a cosine falloff.
+#[derive(Debug, Clone)]
+pub struct RectLight {
+    pub position: Point,
+    pub u: Vector,
+    pub v: Vector,
+    pub color: Color,
+    pub intensity: f64,
+    pub samples: u32,
+}
+
+impl RectLight {
+    pub fn new(
+        position: Point,
+        u: Vector,
+        v: Vector,
+        color: Color,
+        intensity: f64,
+        samples: u32,
+    ) -> RectLight {
+        RectLight {
+            position,
+            u,
+            v,
+            color,
+            intensity,
+            samples,
+        }
+    }
+
+    pub fn with_position(&self, position: Point) -> RectLight {
+        let mut light = self.clone();
+        light.position = position;
+        light
+    }
+
+    pub fn with_edges(&self, u: Vector, v: Vector) -> RectLight {
+        let mut light = self.clone();
+        light.u = u;
+        light.v = v;
+        light
+    }
+
+    pub fn with_color(&self, color: Color) -> RectLight {
+        let mut light = self.clone();
+        light.color = color;
+        light
+    }
+
+    pub fn with_intensity(&self, intensity: f64) -> RectLight {
+        let mut light = self.clone();
+        light.intensity = intensity;
+        light
+    }
+
+    pub fn with_samples(&self, samples: u32) -> RectLight {
+        let mut light = self.clone();
+        light.samples = samples;
+        light
+    }
+
+    pub fn normal(&self) -> Vector {
+        self.u.cross(&self.v).normalize()
+    }
+
+    fn emitted(&self, from: Point, to: Point) -> Color {
+        let distance = from.distance(&to);
+        let cos_light = self.normal().dot(&(from - to).normalize()).max(0.0);
+        self.color * (self.intensity / distance * cos_light)
+    }
+}
+
+impl Default for RectLight {
+    fn default() -> Self {
+        RectLight {
+            position: Point::new(0.0, 0.0, 0.0),
+            u: Vector::new(1.0, 0.0, 0.0),
+            v: Vector::new(0.0, 1.0, 0.0),
+            color: "#FFFFFF".into(),
+            intensity: 10.0,
+            samples: 16,
+        }
+    }
+}
+
+impl Emittable for RectLight {
+    fn intensity(&self, point: Point) -> Color {
+        self.emitted(point, self.position)
+    }
+
+    fn direction(&self, point: Point) -> Vector {
+        (self.position - point).normalize()
+    }
+
+    fn distance(&self, point: Point) -> f64 {
+        self.position.distance(&point)
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples.max(1)
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, point: Point, u: f64, v: f64) -> LightSample {
+        let target = self.position + self.u * (u - 0.5) + self.v * (v - 0.5);
+        LightSample {
+            direction: (target - point).normalize(),
+            distance: target.distance(&point),
+            intensity: self.emitted(point, target),
+        }
+    }
+}
+
+impl From<RectLight> for Light {
+    fn from(rect: RectLight) -> Light {
+        Light::Rect(rect)
+    }
+}
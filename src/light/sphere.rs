@@ -0,0 +1,132 @@
+use crate::utils::{
+    color::Color,
+    math::{point::Point, vector::Vector},
+};
+
+use super::{Emittable, Light, LightSample};
+
+/// Spherical light, a point light with a radius.
+#[derive(Debug, Clone)]
+pub struct SphereLight {
+    pub position: Point,
+    pub radius: f64,
+    pub color: Color,
+    pub intensity: f64,
+    pub samples: u32,
+}
+
+impl SphereLight {
+    pub fn new(
+        position: Point,
+        radius: f64,
+        color: Color,
+        intensity: f64,
+        samples: u32,
+    ) -> SphereLight {
+        SphereLight {
+            position,
+            radius,
+            color,
+            intensity,
+            samples,
+        }
+    }
+
+    pub fn with_position(&self, position: Point) -> SphereLight {
+        let mut light = self.clone();
+        light.position = position;
+        light
+    }
+
+    pub fn with_radius(&self, radius: f64) -> SphereLight {
+        let mut light = self.clone();
+        light.radius = radius;
+        light
+    }
+
+    pub fn with_color(&self, color: Color) -> SphereLight {
+        let mut light = self.clone();
+        light.color = color;
+        light
+    }
+
+    pub fn with_intensity(&self, intensity: f64) -> SphereLight {
+        let mut light = self.clone();
+        light.intensity = intensity;
+        light
+    }
+
+    pub fn with_samples(&self, samples: u32) -> SphereLight {
+        let mut light = self.clone();
+        light.samples = samples;
+        light
+    }
+}
+
+impl Default for SphereLight {
+    fn default() -> Self {
+        SphereLight {
+            position: Point::new(0.0, 0.0, 0.0),
+            radius: 1.0,
+            color: "#FFFFFF".into(),
+            intensity: 10.0,
+            samples: 16,
+        }
+    }
+}
+
+impl Emittable for SphereLight {
+    fn intensity(&self, point: Point) -> Color {
+        self.color * (self.intensity / self.position.distance(&point))
+    }
+
+    fn direction(&self, point: Point) -> Vector {
+        (self.position - point).normalize()
+    }
+
+    fn distance(&self, point: Point) -> f64 {
+        self.position.distance(&point)
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples.max(1)
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn sample(&self, point: Point, u: f64, v: f64) -> LightSample {
+        let distance = self.position.distance(&point);
+        if distance <= self.radius {
+            return LightSample {
+                direction: self.direction(point),
+                distance,
+                intensity: self.intensity(point),
+            };
+        }
+
+        // Sample the cone of directions under which the sphere is visible
+        let sin_max = self.radius / distance;
+        let cos_max = (1.0 - sin_max * sin_max).max(0.0).sqrt();
+        let direction = self.direction(point).uniform_cone(cos_max, u, v);
+        let cos_theta = direction.dot(&self.direction(point));
+        let sin2_theta = (1.0 - cos_theta * cos_theta).max(0.0);
+        let surface = distance * cos_theta
+            - (self.radius * self.radius - distance * distance * sin2_theta)
+                .max(0.0)
+                .sqrt();
+
+        LightSample {
+            direction,
+            distance: surface,
+            intensity: self.intensity(point),
+        }
+    }
+}
+
+impl From<SphereLight> for Light {
+    fn from(sphere: SphereLight) -> Light {
+        Light::Sphere(sphere)
+    }
+}
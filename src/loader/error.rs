@@ -0,0 +1,158 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    /// The scene file could not be read.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The scene is not valid JSON or does not match the scene format.
+    Parse {
+        field: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// The scene is well formed but a value makes no sense, e.g. an unknown material name.
+    Invalid { field: String, message: String },
+    /// A Wavefront file referenced by the scene could not be loaded.
+    Obj { field: String, source: ObjError },
+    /// An image referenced by the scene could not be loaded.
+    Image {
+        field: String,
+        path: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+impl SceneError {
+    pub fn invalid(field: &str, message: impl Into<String>) -> SceneError {
+        SceneError::Invalid {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            SceneError::Parse {
+                field,
+                line,
+                column,
+                message,
+            } => {
+                if field.is_empty() || field == "." {
+                    write!(f, "line {}, column {}: {}", line, column, message)
+                } else {
+                    write!(
+                        f,
+                        "{} (line {}, column {}): {}",
+                        field, line, column, message
+                    )
+                }
+            }
+            SceneError::Invalid { field, message } => write!(f, "{}: {}", field, message),
+            SceneError::Obj { field, source } => write!(f, "{}: {}", field, source),
+            SceneError::Image {
+                field,
+                path,
+                source,
+            } => write!(
+                f,
+                "{}: could not load {}: {}",
+                field,
+                path.display(),
+                source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Obj { source, .. } => Some(source),
+            SceneError::Image { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for SceneError {
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> SceneError {
+        let field = error.path().to_string();
+        let inner = error.into_inner();
+        // serde_json appends the position to its messages, it is reported separately
+        let message = inner.to_string();
+        let message = match message.rfind(" at line ") {
+            Some(index) if inner.line() > 0 => message[..index].to_string(),
+            _ => message,
+        };
+
+        SceneError::Parse {
+            field,
+            line: inner.line(),
+            column: inner.column(),
+            message,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ObjError {
+    /// An OBJ or MTL file could not be read.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A statement of an OBJ or MTL file is malformed.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+impl ObjError {
+    pub fn parse(path: &Path, line: usize, message: impl Into<String>) -> ObjError {
+        ObjError::Parse {
+            path: path.to_path_buf(),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => {
+                write!(f, "could not read {}: {}", path.display(), source)
+            }
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
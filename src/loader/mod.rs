@@ -0,0 +1,25 @@
+use std::{
+    collections::HashSet,
+    sync::{Mutex, OnceLock, PoisonError},
+};
+
+pub mod error;
+pub mod mtl;
+pub mod obj;
+pub mod scene;
+
+/// Material names are `&'static str` so that materials stay `Copy`. Names coming from files are
+/// interned, each distinct name is leaked the first time it's loaded and reused afterwards.
+pub(crate) fn intern(name: String) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut names = NAMES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(name) = names.get(name.as_str()) {
+        return name;
+    }
+    let name: &'static str = Box::leak(name.into_boxed_str());
+    names.insert(name);
+    name
+}
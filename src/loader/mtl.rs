@@ -0,0 +1,164 @@
+//! Wavefront MTL material libraries.
+//!
+//! Supported statements are `newmtl`, `Ka`, `Kd`, `Ks`, `Ns`, `Ni`, `d`, `Tr` and `illum`, other
+//! statements are ignored. Illumination models with ray traced reflections (3, 5 and 7) use `Ks`
+//! as reflection color.
+
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    material::Material,
+    utils::{color::Color, color_or_float::ColorOrFloat},
+};
+
+use super::{error::ObjError, intern};
+
+#[derive(Debug, Default)]
+struct MtlEntry {
+    name: String,
+    ambient: Option<Color>,
+    diffuse: Option<Color>,
+    specular: Option<Color>,
+    specular_exponent: Option<f64>,
+    refraction_index: Option<f64>,
+    dissolve: Option<f64>,
+    illumination: Option<u32>,
+}
+
+impl MtlEntry {
+    fn into_material(self) -> Material {
+        let mut material = Material::default()
+            .with_name(intern(self.name))
+            .with_diffuse(ColorOrFloat::Float(1.0));
+        if let Some(color) = self.diffuse {
+            material = material.with_color(color);
+        }
+        if let Some(ambient) = self.ambient {
+            material = material.with_ambient(ambient.into());
+        }
+        let specular = self.specular.map_or(material.specular, ColorOrFloat::Color);
+        material = material.with_specular(
+            specular,
+            self.specular_exponent.unwrap_or(material.specular_exponent),
+        );
+        if let Some(illumination) = self.illumination {
+            let reflection = match illumination {
+                3 | 5 | 7 => specular,
+                _ => ColorOrFloat::Float(0.0),
+            };
+            material = material.with_reflection(reflection);
+        }
+        let transparency = self
+            .dissolve
+            .map_or(material.transparency, |d| ColorOrFloat::Float(1.0 - d));
+        material.with_transparency(
+            transparency,
+            self.refraction_index.unwrap_or(material.refraction_index),
+        )
+    }
+}
+
+pub fn load(path: &Path) -> Result<HashMap<String, Material>, ObjError> {
+    let source = std::fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse(&source, path)
+}
+
+/// Parses the content of an MTL file, `path` is only used to report errors.
+pub fn parse(source: &str, path: &Path) -> Result<HashMap<String, Material>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<MtlEntry> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        if keyword == "newmtl" {
+            if let Some(entry) = current.take() {
+                materials.insert(entry.name.clone(), entry.into_material());
+            }
+            if args.is_empty() {
+                return Err(ObjError::parse(path, line_number, "newmtl without a name"));
+            }
+            current = Some(MtlEntry {
+                name: args.join(" "),
+                ..MtlEntry::default()
+            });
+            continue;
+        }
+
+        let entry = match current.as_mut() {
+            Some(entry) => entry,
+            None if ["Ka", "Kd", "Ks", "Ns", "Ni", "d", "Tr", "illum"].contains(&keyword) => {
+                return Err(ObjError::parse(
+                    path,
+                    line_number,
+                    format!("`{}` before any newmtl", keyword),
+                ))
+            }
+            None => continue,
+        };
+
+        match keyword {
+            "Ka" => entry.ambient = Some(color(&args, path, line_number)?),
+            "Kd" => entry.diffuse = Some(color(&args, path, line_number)?),
+            "Ks" => entry.specular = Some(color(&args, path, line_number)?),
+            "Ns" => entry.specular_exponent = Some(float(&args, path, line_number)?),
+            "Ni" => entry.refraction_index = Some(float(&args, path, line_number)?),
+            "d" => entry.dissolve = Some(float(&args, path, line_number)?),
+            "Tr" => entry.dissolve = Some(1.0 - float(&args, path, line_number)?),
+            "illum" => {
+                let illumination = args
+                    .first()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| {
+                        ObjError::parse(path, line_number, "illum expects an integer")
+                    })?;
+                entry.illumination = Some(illumination);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(entry) = current.take() {
+        materials.insert(entry.name.clone(), entry.into_material());
+    }
+
+    Ok(materials)
+}
+
+fn float(args: &[&str], path: &Path, line: usize) -> Result<f64, ObjError> {
+    // Some exporters write `d -halo 0.5`
+    args.iter()
+        .find(|arg| **arg != "-halo")
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| ObjError::parse(path, line, "expected a number"))
+}
+
+fn color(args: &[&str], path: &Path, line: usize) -> Result<Color, ObjError> {
+    if matches!(args.first(), Some(&"spectral") | Some(&"xyz")) {
+        return Err(ObjError::parse(path, line, "only RGB colors are supported"));
+    }
+    let values = args
+        .iter()
+        .map(|arg| arg.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ObjError::parse(path, line, "expected numbers"))?;
+    match values[..] {
+        [gray] => Ok(Color::new(gray, gray, gray)),
+        [r, g, b] => Ok(Color::new(r, g, b)),
+        _ => Err(ObjError::parse(
+            path,
+            line,
+            format!("expected 1 or 3 color components, got {}", values.len()),
+        )),
+    }
+}
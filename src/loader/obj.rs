@@ -0,0 +1,425 @@
+//! Wavefront OBJ meshes.
+//!
+//! Supported statements are `v`, `vt`, `vn`, `f`, `o`, `g`, `usemtl` and `mtllib`, other
+//! statements (smoothing groups, lines, points...) are ignored. Polygons are triangulated by ear
+//! clipping, faces with a normal on every corner become smooth triangles.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    material::{texture::Texture, Material},
+    objects::mesh::{Face, Mesh},
+    utils::math::{point::Point, vector::Vector},
+};
+
+use super::{error::ObjError, mtl};
+
+/// Faces sharing the same `o` object and `g` group names. The mesh has no BVH yet, call
+/// `Mesh::build_bvh` before intersecting it.
+#[derive(Debug, Clone)]
+pub struct ObjGroup {
+    pub object: Option<String>,
+    pub group: Option<String>,
+    pub mesh: Mesh,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Buffers of the group being parsed, vertices are copied from the whole file on first use.
+#[derive(Default)]
+struct GroupBuilder {
+    mesh: Mesh,
+    positions: HashMap<usize, u32>,
+    uvs: HashMap<usize, u32>,
+    normals: HashMap<usize, u32>,
+    textures: HashMap<String, u32>,
+}
+
+struct Parser<'a> {
+    path: &'a Path,
+    positions: Vec<Point>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vector>,
+    materials: HashMap<String, Material>,
+    material: Option<String>,
+    object: Option<String>,
+    group: Option<String>,
+    builder: GroupBuilder,
+    groups: Vec<ObjGroup>,
+}
+
+pub fn load(path: &Path) -> Result<Vec<ObjGroup>, ObjError> {
+    let source = std::fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse(&source, path)
+}
+
+/// Loads every group of an OBJ file into a single mesh.
+pub fn load_mesh(path: &Path) -> Result<Mesh, ObjError> {
+    let mut mesh = Mesh::default();
+    for group in load(path)? {
+        mesh.append(&group.mesh);
+    }
+    Ok(mesh.build_bvh())
+}
+
+/// Parses the content of an OBJ file, material libraries are resolved relative to `path`.
+pub fn parse(source: &str, path: &Path) -> Result<Vec<ObjGroup>, ObjError> {
+    let mut parser = Parser {
+        path,
+        positions: Vec::new(),
+        uvs: Vec::new(),
+        normals: Vec::new(),
+        materials: HashMap::new(),
+        material: None,
+        object: None,
+        group: None,
+        builder: GroupBuilder::default(),
+        groups: Vec::new(),
+    };
+
+    for (index, line) in source.lines().enumerate() {
+        parser.statement(line, index + 1)?;
+    }
+    parser.flush();
+
+    Ok(parser.groups)
+}
+
+impl Parser<'_> {
+    fn statement(&mut self, line: &str, line_number: usize) -> Result<(), ObjError> {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+        let args = tokens.collect::<Vec<_>>();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = self.floats(&args, line_number)?;
+                self.positions.push(Point::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = self.floats(&args, line_number)?;
+                self.normals.push(Vector::new(x, y, z).normalize());
+            }
+            "vt" => {
+                let [u] = self.floats(&args, line_number)?;
+                let v = match args.get(1) {
+                    Some(v) => self.float(v, line_number)?,
+                    None => 0.0,
+                };
+                self.uvs.push((u, v));
+            }
+            "f" => self.face(&args, line_number)?,
+            "o" => {
+                self.flush();
+                self.object = (!args.is_empty()).then(|| args.join(" "));
+                self.group = None;
+            }
+            "g" => {
+                self.flush();
+                self.group = (!args.is_empty()).then(|| args.join(" "));
+            }
+            "usemtl" => self.material = Some(args.join(" ")),
+            "mtllib" => {
+                for library in args {
+                    let library = self.relative(library);
+                    self.materials.extend(mtl::load(&library)?);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn relative(&self, file: &str) -> PathBuf {
+        self.path
+            .parent()
+            .map_or_else(|| PathBuf::from(file), |parent| parent.join(file))
+    }
+
+    fn flush(&mut self) {
+        let mesh = std::mem::take(&mut self.builder).mesh;
+        if mesh.faces.is_empty() {
+            return;
+        }
+        self.groups.push(ObjGroup {
+            object: self.object.clone(),
+            group: self.group.clone(),
+            mesh,
+        });
+    }
+
+    /// Index of the current material in the textures of the group.
+    fn texture(&mut self) -> u32 {
+        let name = self.material.clone().unwrap_or_default();
+        let builder = &mut self.builder;
+        if let Some(index) = builder.textures.get(&name) {
+            return *index;
+        }
+        // Unknown names fall back to the default material like most viewers do
+        let texture = self
+            .materials
+            .get(&name)
+            .map_or_else(Texture::default, |material| Texture::new_uniform(*material));
+        builder.mesh.textures.push(texture);
+        let index = (builder.mesh.textures.len() - 1) as u32;
+        builder.textures.insert(name, index);
+        index
+    }
+
+    /// Builds a face, copying the vertices it uses into the group.
+    fn vertices(&mut self, corners: [Corner; 3]) -> Face {
+        let builder = &mut self.builder;
+        let positions = corners.map(|corner| {
+            *builder.positions.entry(corner.position).or_insert_with(|| {
+                builder.mesh.positions.push(self.positions[corner.position]);
+                (builder.mesh.positions.len() - 1) as u32
+            })
+        });
+        let mut face = Face::new(positions);
+
+        if let [Some(a), Some(b), Some(c)] = corners.map(|corner| corner.uv) {
+            face = face.with_uvs([a, b, c].map(|uv| {
+                *builder.uvs.entry(uv).or_insert_with(|| {
+                    builder.mesh.uvs.push(self.uvs[uv]);
+                    (builder.mesh.uvs.len() - 1) as u32
+                })
+            }));
+        }
+        if let [Some(a), Some(b), Some(c)] = corners.map(|corner| corner.normal) {
+            face = face.with_normals([a, b, c].map(|normal| {
+                *builder.normals.entry(normal).or_insert_with(|| {
+                    builder.mesh.normals.push(self.normals[normal]);
+                    (builder.mesh.normals.len() - 1) as u32
+                })
+            }));
+        }
+
+        face
+    }
+
+    fn float(&self, value: &str, line: usize) -> Result<f64, ObjError> {
+        value
+            .parse()
+            .map_err(|_| ObjError::parse(self.path, line, format!("`{}` is not a number", value)))
+    }
+
+    /// Parses the first `N` arguments, extra arguments (`w` coordinates, vertex colors) are ignored.
+    fn floats<const N: usize>(&self, args: &[&str], line: usize) -> Result<[f64; N], ObjError> {
+        if args.len() < N {
+            return Err(ObjError::parse(
+                self.path,
+                line,
+                format!("expected {} numbers, got {}", N, args.len()),
+            ));
+        }
+        let mut values = [0.0; N];
+        for (value, arg) in values.iter_mut().zip(args) {
+            *value = self.float(arg, line)?;
+        }
+        Ok(values)
+    }
+
+    fn index(&self, value: &str, count: usize, kind: &str, line: usize) -> Result<usize, ObjError> {
+        let index: i64 = value.parse().map_err(|_| {
+            ObjError::parse(self.path, line, format!("`{}` is not an index", value))
+        })?;
+        // Indices start at 1, negative indices are relative to the last element
+        let resolved = if index > 0 {
+            index - 1
+        } else {
+            count as i64 + index
+        };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(ObjError::parse(
+                self.path,
+                line,
+                format!("{} index {} out of range ({} defined)", kind, index, count),
+            ));
+        }
+        Ok(resolved as usize)
+    }
+
+    fn corner(&self, token: &str, line: usize) -> Result<Corner, ObjError> {
+        let mut parts = token.split('/');
+        let position = self.index(
+            parts.next().unwrap_or(""),
+            self.positions.len(),
+            "vertex",
+            line,
+        )?;
+        let uv = match parts.next() {
+            Some("") | None => None,
+            Some(uv) => Some(self.index(uv, self.uvs.len(), "texture coordinate", line)?),
+        };
+        let normal = match parts.next() {
+            Some("") | None => None,
+            Some(normal) => Some(self.index(normal, self.normals.len(), "normal", line)?),
+        };
+        Ok(Corner {
+            position,
+            uv,
+            normal,
+        })
+    }
+
+    fn face(&mut self, args: &[&str], line: usize) -> Result<(), ObjError> {
+        if args.len() < 3 {
+            return Err(ObjError::parse(
+                self.path,
+                line,
+                format!("a face needs at least 3 vertices, got {}", args.len()),
+            ));
+        }
+        let corners = args
+            .iter()
+            .map(|token| self.corner(token, line))
+            .collect::<Result<Vec<_>, _>>()?;
+        let points = corners
+            .iter()
+            .map(|corner| self.positions[corner.position])
+            .collect::<Vec<_>>();
+
+        let texture = self.texture();
+        for [a, b, c] in triangulate(&points) {
+            let face = self.vertices([corners[a], corners[b], corners[c]]);
+            self.builder.mesh.faces.push(face.with_texture(texture));
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits a planar polygon into triangles by ear clipping, falling back to a fan for degenerate input.
+fn triangulate(points: &[Point]) -> Vec<[usize; 3]> {
+    let fan = || (1..points.len() - 1).map(|i| [0, i, i + 1]).collect();
+    if points.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Newell's method gives a robust normal even for non-convex polygons
+    let mut normal = Vector::zero();
+    for (i, p) in points.iter().enumerate() {
+        let q = points[(i + 1) % points.len()];
+        normal.x += (p.y - q.y) * (p.z + q.z);
+        normal.y += (p.z - q.z) * (p.x + q.x);
+        normal.z += (p.x - q.x) * (p.y + q.y);
+    }
+    if normal.length_squared() < 1e-24 {
+        return fan();
+    }
+
+    // Project on the plane of the two other axes, keeping the polygon counter-clockwise
+    let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let project = |p: &Point| -> (f64, f64) {
+        if az >= ax && az >= ay {
+            (p.x, p.y * normal.z.signum())
+        } else if ax >= ay {
+            (p.y, p.z * normal.x.signum())
+        } else {
+            (p.z, p.x * normal.y.signum())
+        }
+    };
+    let projected = points.iter().map(project).collect::<Vec<_>>();
+    let cross = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+        (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+    };
+
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            let (pa, pb, pc) = (projected[a], projected[b], projected[c]);
+            if cross(pa, pb, pc) <= 0.0 {
+                return false;
+            }
+            remaining.iter().all(|&j| {
+                j == a
+                    || j == b
+                    || j == c
+                    || cross(pa, pb, projected[j]) < 0.0
+                    || cross(pb, pc, projected[j]) < 0.0
+                    || cross(pc, pa, projected[j]) < 0.0
+            })
+        });
+
+        match ear {
+            Some(i) => {
+                triangles.push([
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                ]);
+                remaining.remove(i);
+            }
+            None => return fan(),
+        }
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(a: Point, b: Point, c: Point) -> f64 {
+        (b - a).cross(&(c - a)).z / 2.0
+    }
+
+    #[test]
+    fn ear_clipping_keeps_concave_polygons_inside() {
+        // A dart whose notch a fan from the first vertex would cover
+        let points = [(0.0, 0.0), (2.0, 1.0), (0.0, 2.0), (1.0, 1.0)]
+            .map(|(x, y)| Point::new(x, y, 0.0));
+        let triangles = triangulate(&points);
+
+        assert_eq!(triangles.len(), 2);
+        let areas = triangles
+            .iter()
+            .map(|&[a, b, c]| area(points[a], points[b], points[c]))
+            .collect::<Vec<_>>();
+        assert!(areas.iter().all(|area| *area > 0.0), "{:?}", areas);
+        assert!((areas.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn negative_indices_are_relative_to_the_last_element() {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\n";
+        let absolute = format!("{}f 1/1 2/2 3/1 4/2\n", vertices);
+        let relative = format!("{}f -4/-2 -3/-1 -2/-2 -1/-1\n", vertices);
+
+        let absolute = parse(&absolute, Path::new("absolute.obj")).unwrap();
+        let relative = parse(&relative, Path::new("relative.obj")).unwrap();
+        assert_eq!(relative[0].mesh.faces.len(), 2);
+        assert_eq!(format!("{:?}", absolute), format!("{:?}", relative));
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        let source = "v 0 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 -4\n";
+        assert!(parse(source, Path::new("invalid.obj")).is_err());
+    }
+}
@@ -0,0 +1,1469 @@
+//! JSON scene format.
+//!
+//! A scene file is a JSON object whose fields all have defaults:
+//!
+//! ```json
+//! {
+//!     "camera": { "position": [0, 0, 0], "direction": [1, 0, 0], "anti_aliasing": 16 },
+//!     "background": "#1CB5E0",
+//!     "materials": { "glass": { "transparency": 1.0, "refraction_index": 1.5 } },
+//!     "objects": [
+//!         { "type": "sphere", "center": [5, 0, 0], "radius": 1, "material": "glass" }
+//!     ],
+//!     "lights": [
+//!         { "type": "point", "position": [5, 10, 10], "color": "#00FF00", "intensity": 10 }
+//!     ]
+//! }
+//! ```
+//!
+//! Colors are either `"#RRGGBB"` strings or linear `[r, g, b]` arrays, points and vectors are
+//! `[x, y, z]` arrays and angles are in degrees. Wherever a material is expected, the name of an
+//! entry of `materials` can be used instead of an inline material. Objects take either a `texture`
+//! or a `material`, which is a shorthand for a uniform texture.
+//!
+//! Textures are `uniform` (a `material`), `checkerboard` (`material1`, `material2` and `scale`)
+//! or `image`, which maps a PNG, JPEG, HDR or EXR picture at `path`, relative to the scene file,
+//! on the texture coordinates:
+//!
+//! ```json
+//! { "type": "image", "path": "wood.png", "filter": "bicubic", "addressing": "mirror", "scale": 4,
+//!   "material": { "specular": 0.2 } }
+//! ```
+//!
+//! The picture replaces the `parameter` (`color`, the default, `ambient`, `diffuse`, `specular`,
+//! `reflection` or `transparency`) of the material given by the `material` or nested `texture`
+//! field. It is sampled with a `nearest`, `bilinear` (the default) or `bicubic` `filter`, and
+//! `addressing` tiles it (`wrap`, the default), stretches its border (`clamp`) or flips every
+//! other copy (`mirror`). `scale` repeats it per unit of texture coordinates. 8-bit pictures
+//! are decoded from sRGB when `srgb` is true, the default for colors only, HDR and EXR files are
+//! linear.
+//!
+//! Spheres are mapped with `u` around their `y` axis and `v` from the bottom (0) to the top (1).
+//! Planes are mapped in world units divided by their `scale`, `v` along the projection of `y` on
+//! the plane (of `x` for floors and ceilings) and `u` to its right seen from the front, so a floor
+//! facing up gets `z` and `x`. Triangles take per-vertex `uvs` (`[[u, v], ...]`), (0, 0), (1, 0) and
+//! (0, 1) by default, and meshes use the `vt` coordinates of their Wavefront files.
+//!
+//! The camera looks along `direction` or at a `target` point, `up` only has to roughly point upwards
+//! and `roll` turns the image counter-clockwise in degrees. With a `vertical_fov` alone (60° by
+//! default) the horizontal field of view follows the image size, a `horizontal_fov` fixes it.
+//!
+//! The camera becomes a thin lens with depth of field when its `aperture` radius is positive. It
+//! focuses at `focus_distance`, or on whatever is at the center of the image with `"auto"`, and
+//! `blades` (with `blade_rotation` in degrees) gives polygonal bokeh. The blur is sampled by the
+//! anti-aliasing rays.
+//!
+//! Pixel, lens, light and bounce sampling is driven by the camera `sampler`: `random`,
+//! `stratified`, `halton`, `sobol` (Owen scrambled, the default) or `blue_noise`, which trades
+//! blotchy noise for a fine grain at low sample counts. Renders are deterministic, the integer
+//! `seed` (0 by default) picks another set of samples.
+//!
+//! Every pixel takes `anti_aliasing` samples, weighted by the camera `filter`: `box` (radius 0.5,
+//! the default), `tent` (1), `gaussian` (1.5, with a `sigma` of 0.5), `mitchell` (2, with `b` and
+//! `c` of 1/3) or `lanczos` (3), e.g. `{ "type": "gaussian", "radius": 2 }`. Sampling becomes
+//! adaptive with `"adaptive": { "threshold": 0.02, "min_samples": 16, "max_samples": 1024 }`:
+//! pixels whose relative noise is above the threshold keep getting samples, up to
+//! `max_samples`, and `anti_aliasing` is ignored.
+//!
+//! The camera `projection` defaults to `{ "type": "perspective" }`, other projections are
+//! `orthographic` (with the `width` of the view), `fisheye_equidistant`, `fisheye_equisolid` and
+//! `cylindrical` (with a horizontal `fov`) and `equirectangular` for 360° panoramas. Fisheyes
+//! only cover the circle inscribed in the width of the image, the rest stays black.
+//!
+//! Geometry declared under `geometry` is shared by every `instance` referencing it by name:
+//!
+//! ```json
+//! {
+//!     "geometry": { "teapot": { "type": "obj", "path": "teapot.obj" } },
+//!     "objects": [
+//!         { "type": "instance", "object": "teapot", "transform": [
+//!             { "scale": 2 }, { "rotate": { "axis": [0, 0, 1], "angle": 90 } }, { "translate": [5, 0, 0] }
+//!         ] }
+//!     ]
+//! }
+//! ```
+//!
+//! Transform steps are applied in order, `scale` takes a number or an `[x, y, z]` array and
+//! `matrix` a row-major 4x4 affine matrix. Instances can also wrap an inline object.
+//!
+//! Besides `point`, `spot` and `directional` lights, `rect` (a `position` at its center and two
+//! edge vectors `u` and `v`, emitting towards `u × v`), `disk` (`position`, `normal`, `radius`) and
+//! `sphere` (`position`, `radius`) area lights cast soft shadows. They are estimated with `samples`
+//! shadow rays per shading point (16 by default). A directional light with an `angular_diameter`
+//! in degrees, 0.53 for the sun, also gets soft shadows.
+//!
+//! An equirectangular panorama given as `"environment": { "path": "sky.hdr" }` replaces the
+//! background and lights the scene. HDR and EXR files hold linear radiance, other formats are
+//! gamma decoded. `intensity` scales it, `rotation` turns it in degrees around `up` (`[0, 1, 0]`
+//! by default) and `samples` sets the number of shadow rays, the center of the image faces `x`.
+//!
+//! Wavefront files are imported with `{ "type": "obj", "path": "teapot.obj" }`, the path being
+//! relative to the scene file. Their MTL materials are used unless a `texture` or `material` is
+//! given.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::{
+    engine::{
+        adaptive::AdaptiveSampling, camera::Camera, filter::Filter, integrator::Integrator,
+        projection::Projection, sampler::Sampler, scene::Scene,
+    },
+    generators::metaball::{Metaball, MetaballPoint},
+    light::{
+        directional::DirectionalLight,
+        disk::DiskLight,
+        environment::{EnvironmentLight, EnvironmentMap},
+        point::PointLight,
+        rect::RectLight,
+        sphere::SphereLight,
+        spot::SpotLight,
+        Light,
+    },
+    material::{
+        fresnel::Fresnel,
+        texture::{
+            checkerboard::CheckerboardTexture,
+            image_texture::{
+                Addressing, ImageTexture, TextureFilter, TextureImage, TextureParameter,
+            },
+            Texture,
+        },
+        Material,
+    },
+    objects::{
+        instance::Instance, mesh::Mesh, plane::Plane, smooth_triangle::SmoothTriangle,
+        sphere::Sphere, triangle::Triangle, Object,
+    },
+    utils::{
+        color::Color,
+        color_or_float::ColorOrFloat,
+        math::{point::Point, transform::Transform, vector::Vector},
+    },
+};
+
+use super::{error::SceneError, intern, obj};
+
+pub fn from_str(source: &str) -> Result<Scene, SceneError> {
+    from_source(source, Path::new(""))
+}
+
+/// Parses a scene, files referenced by the scene are resolved relative to `base_dir`.
+pub fn from_source(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(source);
+    let description: SceneDescription = serde_path_to_error::deserialize(deserializer)?;
+    description.build(base_dir)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    pub background: Option<ColorValue>,
+    pub materials: HashMap<String, MaterialDescription>,
+    pub geometry: HashMap<String, ObjectDescription>,
+    pub objects: Vec<ObjectDescription>,
+    pub lights: Vec<LightDescription>,
+    pub environment: Option<EnvironmentDescription>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraDescription {
+    pub position: Option<[f64; 3]>,
+    pub direction: Option<[f64; 3]>,
+    pub target: Option<[f64; 3]>,
+    pub up: Option<[f64; 3]>,
+    pub roll: Option<f64>,
+    pub horizontal_fov: Option<f64>,
+    pub vertical_fov: Option<f64>,
+    pub z_min: Option<f64>,
+    pub max_bounces: Option<u32>,
+    pub anti_aliasing: Option<u32>,
+    pub filter: Option<FilterDescription>,
+    pub adaptive: Option<AdaptiveDescription>,
+    pub integrator: Option<IntegratorDescription>,
+    pub sampler: Option<SamplerDescription>,
+    pub seed: Option<u64>,
+    pub aperture: Option<f64>,
+    pub focus_distance: Option<FocusDescription>,
+    pub blades: Option<u32>,
+    pub blade_rotation: Option<f64>,
+    pub projection: Option<ProjectionDescription>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ProjectionDescription {
+    Perspective,
+    Orthographic { width: f64 },
+    FisheyeEquidistant { fov: f64 },
+    FisheyeEquisolid { fov: f64 },
+    Cylindrical { fov: f64 },
+    Equirectangular,
+}
+
+/// Reconstruction filter, parameters left out take the defaults of `Filter`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum FilterDescription {
+    Box {
+        radius: Option<f64>,
+    },
+    Tent {
+        radius: Option<f64>,
+    },
+    Gaussian {
+        radius: Option<f64>,
+        sigma: Option<f64>,
+    },
+    Mitchell {
+        radius: Option<f64>,
+        b: Option<f64>,
+        c: Option<f64>,
+    },
+    Lanczos {
+        radius: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdaptiveDescription {
+    pub threshold: f64,
+    pub min_samples: Option<u32>,
+    pub max_samples: Option<u32>,
+}
+
+/// A focus distance, or `"auto"` to focus on what is at the center of the image.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum FocusDescription {
+    Distance(f64),
+    Mode(FocusMode),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusMode {
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegratorDescription {
+    Whitted,
+    PathTracing,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplerDescription {
+    Random,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialDescription {
+    pub name: Option<String>,
+    pub color: Option<ColorValue>,
+    pub ambient: Option<ColorOrFloatValue>,
+    pub reflection: Option<ColorOrFloatValue>,
+    pub diffuse: Option<ColorOrFloatValue>,
+    pub specular: Option<ColorOrFloatValue>,
+    pub specular_exponent: Option<f64>,
+    pub transparency: Option<ColorOrFloatValue>,
+    pub refraction_index: Option<f64>,
+    pub absorption: Option<f64>,
+    pub fresnel: Option<FresnelDescription>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FresnelDescription {
+    Schlick,
+    Exact,
+}
+
+/// Either the name of an entry of `materials` or an inline material.
+#[derive(Debug, Clone)]
+pub enum MaterialRef {
+    Named(String),
+    Inline(Box<MaterialDescription>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDescription {
+    Uniform {
+        material: MaterialRef,
+    },
+    Checkerboard {
+        material1: MaterialRef,
+        material2: MaterialRef,
+        #[serde(default = "default_scale")]
+        scale: f64,
+    },
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        filter: TextureFilterDescription,
+        #[serde(default)]
+        addressing: AddressingDescription,
+        #[serde(default)]
+        parameter: TextureParameterDescription,
+        /// Defaults to decoding colors only.
+        srgb: Option<bool>,
+        #[serde(default = "default_scale")]
+        scale: f64,
+        texture: Option<Box<TextureDescription>>,
+        material: Option<MaterialRef>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureFilterDescription {
+    Nearest,
+    #[default]
+    Bilinear,
+    Bicubic,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressingDescription {
+    #[default]
+    Wrap,
+    Clamp,
+    Mirror,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureParameterDescription {
+    #[default]
+    Color,
+    Ambient,
+    Diffuse,
+    Specular,
+    Reflection,
+    Transparency,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        texture: Option<TextureDescription>,
+        material: Option<MaterialRef>,
+    },
+    Plane {
+        position: [f64; 3],
+        normal: [f64; 3],
+        #[serde(default = "default_scale")]
+        scale: f64,
+        texture: Option<TextureDescription>,
+        material: Option<MaterialRef>,
+    },
+    Triangle {
+        points: [[f64; 3]; 3],
+        uvs: Option<[[f64; 2]; 3]>,
+        texture: Option<TextureDescription>,
+        material: Option<MaterialRef>,
+    },
+    SmoothTriangle {
+        points: [[f64; 3]; 3],
+        normals: [[f64; 3]; 3],
+        uvs: Option<[[f64; 2]; 3]>,
+        texture: Option<TextureDescription>,
+        material: Option<MaterialRef>,
+    },
+    Mesh {
+        faces: Vec<ObjectDescription>,
+    },
+    Obj {
+        path: PathBuf,
+        texture: Option<TextureDescription>,
+        material: Option<MaterialRef>,
+    },
+    Instance {
+        object: ObjectRef,
+        #[serde(default)]
+        transform: Vec<TransformDescription>,
+    },
+    Metaball {
+        bounds: [[f64; 3]; 2],
+        resolution: f64,
+        fronteer: f64,
+        points: Vec<MetaballPointDescription>,
+        texture: Option<TextureDescription>,
+        material: Option<MaterialRef>,
+    },
+}
+
+/// Either the name of an entry of `geometry` or an inline object.
+#[derive(Debug, Clone)]
+pub enum ObjectRef {
+    Named(String),
+    Inline(Box<ObjectDescription>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum TransformDescription {
+    Translate([f64; 3]),
+    Scale(ScaleDescription),
+    Rotate { axis: [f64; 3], angle: f64 },
+    Matrix([[f64; 4]; 4]),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ScaleDescription {
+    Uniform(f64),
+    Axes([f64; 3]),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetaballPointDescription {
+    pub position: [f64; 3],
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LightDescription {
+    Directional {
+        direction: [f64; 3],
+        color: Option<ColorValue>,
+        intensity: Option<f64>,
+        angular_diameter: Option<f64>,
+        samples: Option<u32>,
+    },
+    Point {
+        position: [f64; 3],
+        color: Option<ColorValue>,
+        intensity: Option<f64>,
+    },
+    Spot {
+        position: [f64; 3],
+        direction: [f64; 3],
+        color: Option<ColorValue>,
+        intensity: Option<f64>,
+        cutoff: Option<f64>,
+        falloff: Option<f64>,
+    },
+    Rect {
+        position: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        color: Option<ColorValue>,
+        intensity: Option<f64>,
+        samples: Option<u32>,
+    },
+    Disk {
+        position: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        color: Option<ColorValue>,
+        intensity: Option<f64>,
+        samples: Option<u32>,
+    },
+    Sphere {
+        position: [f64; 3],
+        radius: f64,
+        color: Option<ColorValue>,
+        intensity: Option<f64>,
+        samples: Option<u32>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentDescription {
+    pub path: PathBuf,
+    pub intensity: Option<f64>,
+    pub rotation: Option<f64>,
+    pub up: Option<[f64; 3]>,
+    pub samples: Option<u32>,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_radius() -> f64 {
+    1.0
+}
+
+fn vector(field: &str, value: [f64; 3]) -> Result<Vector, SceneError> {
+    let vector = Vector::from(value);
+    if vector.length_squared() == 0.0 {
+        return Err(SceneError::invalid(field, "vector must not be zero"));
+    }
+    Ok(vector.normalize())
+}
+
+fn light_samples(field: &str, samples: Option<u32>, default: u32) -> Result<u32, SceneError> {
+    match samples {
+        Some(0) => Err(SceneError::invalid(
+            &format!("{}.samples", field),
+            "samples must be positive",
+        )),
+        Some(samples) => Ok(samples),
+        None => Ok(default),
+    }
+}
+
+fn light_radius(field: &str, radius: f64) -> Result<f64, SceneError> {
+    if radius <= 0.0 {
+        return Err(SceneError::invalid(
+            &format!("{}.radius", field),
+            "radius must be positive",
+        ));
+    }
+    Ok(radius)
+}
+
+struct Context<'a> {
+    materials: HashMap<String, Material>,
+    geometry: HashMap<String, Arc<Object>>,
+    base_dir: &'a Path,
+    /// Pictures already loaded by image textures, with the sRGB flag they were decoded with.
+    images: RefCell<HashMap<(PathBuf, bool), Arc<TextureImage>>>,
+}
+
+impl SceneDescription {
+    pub fn build(self, base_dir: &Path) -> Result<Scene, SceneError> {
+        let mut materials = HashMap::new();
+        for (name, description) in self.materials {
+            let field = format!("materials.{}", name);
+            let description = MaterialDescription {
+                name: description.name.clone().or_else(|| Some(name.clone())),
+                ..description
+            };
+            materials.insert(name, description.build(&field)?);
+        }
+        let mut context = Context {
+            materials,
+            geometry: HashMap::new(),
+            base_dir,
+            images: RefCell::new(HashMap::new()),
+        };
+
+        // Geometry is built before the objects, it cannot reference other geometry
+        let mut geometry = HashMap::new();
+        for (name, object) in self.geometry {
+            let object = object.build(&context, &format!("geometry.{}", name))?;
+            geometry.insert(name, Arc::new(object));
+        }
+        context.geometry = geometry;
+
+        let objects = self
+            .objects
+            .into_iter()
+            .enumerate()
+            .map(|(i, object)| object.build(&context, &format!("objects[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut lights = self
+            .lights
+            .into_iter()
+            .enumerate()
+            .map(|(i, light)| light.build(&format!("lights[{}]", i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(environment) = self.environment {
+            lights.push(environment.build(base_dir, "environment")?);
+        }
+
+        let background = self.background.map_or(Color::zero(), |color| color.0);
+
+        let autofocus = matches!(
+            self.camera.focus_distance,
+            Some(FocusDescription::Mode(FocusMode::Auto))
+        );
+        let mut scene = Scene::new(self.camera.build("camera")?, objects, lights, background);
+        if autofocus {
+            scene.camera = scene.camera.with_autofocus(&scene);
+        }
+        Ok(scene)
+    }
+}
+
+impl CameraDescription {
+    pub fn build(self, field: &str) -> Result<Camera, SceneError> {
+        let mut camera = Camera::default();
+        if let Some(position) = self.position {
+            camera = camera.with_position(position.into());
+        }
+        if let Some(up) = self.up {
+            camera = camera.with_up(vector(&format!("{}.up", field), up)?);
+        }
+        match (self.direction, self.target) {
+            (Some(_), Some(_)) => {
+                return Err(SceneError::invalid(
+                    &format!("{}.target", field),
+                    "a camera takes either a direction or a target",
+                ))
+            }
+            (Some(direction), None) => {
+                camera = camera.with_direction(vector(&format!("{}.direction", field), direction)?);
+            }
+            (None, Some(target)) => {
+                let direction = Point::from(target) - camera.position;
+                camera =
+                    camera.with_direction(vector(&format!("{}.target", field), direction.into())?);
+            }
+            (None, None) => {}
+        }
+        if let Some(roll) = self.roll {
+            camera = camera.with_roll(roll);
+        }
+        if let Some(fov) = self.horizontal_fov {
+            camera = camera.with_alpha(fov);
+        }
+        if let Some(fov) = self.vertical_fov {
+            camera = camera.with_beta(fov);
+        }
+        if let Some(z_min) = self.z_min {
+            camera = camera.with_z_min(z_min);
+        }
+        if let Some(max_bounces) = self.max_bounces {
+            camera = camera.with_max_bounces(max_bounces);
+        }
+        if let Some(anti_aliasing) = self.anti_aliasing {
+            camera = camera.with_anti_aliasing(anti_aliasing);
+        }
+        if let Some(filter) = self.filter {
+            camera = camera.with_filter(filter.build(&format!("{}.filter", field))?);
+        }
+        if let Some(adaptive) = self.adaptive {
+            camera = camera.with_adaptive(adaptive.build(&format!("{}.adaptive", field))?);
+        }
+        if let Some(aperture) = self.aperture {
+            if aperture < 0.0 {
+                return Err(SceneError::invalid(
+                    &format!("{}.aperture", field),
+                    "aperture must not be negative",
+                ));
+            }
+            camera = camera.with_aperture(aperture);
+        }
+        if let Some(FocusDescription::Distance(distance)) = self.focus_distance {
+            if distance <= 0.0 {
+                return Err(SceneError::invalid(
+                    &format!("{}.focus_distance", field),
+                    "focus distance must be positive",
+                ));
+            }
+            camera = camera.with_focus_distance(distance);
+        }
+        if self.blades.is_some() || self.blade_rotation.is_some() {
+            camera = camera.with_blades(
+                self.blades.unwrap_or(camera.blades),
+                self.blade_rotation.unwrap_or(camera.blade_rotation),
+            );
+        }
+        if let Some(projection) = self.projection {
+            camera = camera.with_projection(projection.build(&format!("{}.projection", field))?);
+        }
+        if let Some(integrator) = self.integrator {
+            camera = camera.with_integrator(match integrator {
+                IntegratorDescription::Whitted => Integrator::Whitted,
+                IntegratorDescription::PathTracing => Integrator::PathTracing,
+            });
+        }
+        if let Some(sampler) = self.sampler {
+            camera = camera.with_sampler(match sampler {
+                SamplerDescription::Random => Sampler::new_random(0),
+                SamplerDescription::Stratified => Sampler::new_stratified(0),
+                SamplerDescription::Halton => Sampler::new_halton(0),
+                SamplerDescription::Sobol => Sampler::new_sobol(0),
+                SamplerDescription::BlueNoise => Sampler::new_blue_noise(0),
+            });
+        }
+        if let Some(seed) = self.seed {
+            camera = camera.with_sampler(camera.sampler.with_seed(seed));
+        }
+        Ok(camera)
+    }
+}
+
+impl MaterialDescription {
+    pub fn build(self, field: &str) -> Result<Material, SceneError> {
+        let mut material = Material::default();
+        if let Some(name) = self.name {
+            material = material.with_name(intern(name));
+        }
+        if let Some(color) = self.color {
+            material = material.with_color(color.0);
+        }
+        if let Some(ambient) = self.ambient {
+            material = material.with_ambient(ambient.0);
+        }
+        if let Some(reflection) = self.reflection {
+            material = material.with_reflection(reflection.0);
+        }
+        if let Some(diffuse) = self.diffuse {
+            material = material.with_diffuse(diffuse.0);
+        }
+        if let Some(specular) = self.specular {
+            material = material.with_specular(specular.0, material.specular_exponent);
+        }
+        if let Some(exponent) = self.specular_exponent {
+            material = material.with_specular(material.specular, exponent);
+        }
+        if let Some(transparency) = self.transparency {
+            material = material.with_transparency(transparency.0, material.refraction_index);
+        }
+        if let Some(refraction_index) = self.refraction_index {
+            if refraction_index <= 0.0 {
+                return Err(SceneError::invalid(
+                    &format!("{}.refraction_index", field),
+                    "refraction index must be positive",
+                ));
+            }
+            material = material.with_transparency(material.transparency, refraction_index);
+        }
+        if let Some(absorption) = self.absorption {
+            material = material.with_absorption(absorption);
+        }
+        if let Some(fresnel) = self.fresnel {
+            material = material.with_fresnel(match fresnel {
+                FresnelDescription::Schlick => Fresnel::Schlick,
+                FresnelDescription::Exact => Fresnel::Exact,
+            });
+        }
+        Ok(material)
+    }
+}
+
+impl MaterialRef {
+    fn build(self, context: &Context, field: &str) -> Result<Material, SceneError> {
+        match self {
+            MaterialRef::Named(name) => {
+                context.materials.get(&name).copied().ok_or_else(|| {
+                    SceneError::invalid(field, format!("unknown material `{}`", name))
+                })
+            }
+            MaterialRef::Inline(description) => (*description).build(field),
+        }
+    }
+}
+
+impl ObjectRef {
+    fn build(self, context: &Context, field: &str) -> Result<Arc<Object>, SceneError> {
+        match self {
+            ObjectRef::Named(name) => {
+                context.geometry.get(&name).cloned().ok_or_else(|| {
+                    SceneError::invalid(field, format!("unknown geometry `{}`", name))
+                })
+            }
+            ObjectRef::Inline(description) => Ok(Arc::new((*description).build(context, field)?)),
+        }
+    }
+}
+
+impl TransformDescription {
+    fn build(self, field: &str) -> Result<Transform, SceneError> {
+        match self {
+            TransformDescription::Translate(offset) => Ok(Transform::translation(offset.into())),
+            TransformDescription::Scale(scale) => {
+                let [x, y, z] = match scale {
+                    ScaleDescription::Uniform(factor) => [factor; 3],
+                    ScaleDescription::Axes(factors) => factors,
+                };
+                if x == 0.0 || y == 0.0 || z == 0.0 {
+                    return Err(SceneError::invalid(field, "scale factors must not be zero"));
+                }
+                Ok(Transform::scaling(x, y, z))
+            }
+            TransformDescription::Rotate { axis, angle } => Ok(Transform::rotation(
+                vector(&format!("{}.axis", field), axis)?,
+                angle,
+            )),
+            TransformDescription::Matrix(matrix) => {
+                if matrix[3] != [0.0, 0.0, 0.0, 1.0] {
+                    return Err(SceneError::invalid(
+                        field,
+                        "the last row of an affine matrix must be [0, 0, 0, 1]",
+                    ));
+                }
+                Transform::from_matrix(matrix)
+                    .ok_or_else(|| SceneError::invalid(field, "matrix is not invertible"))
+            }
+        }
+    }
+}
+
+impl TextureDescription {
+    fn build(self, context: &Context, field: &str) -> Result<Texture, SceneError> {
+        match self {
+            TextureDescription::Uniform { material } => Ok(Texture::new_uniform(
+                material.build(context, &format!("{}.material", field))?,
+            )),
+            TextureDescription::Checkerboard {
+                material1,
+                material2,
+                scale,
+            } => Ok(CheckerboardTexture::new(
+                material1.build(context, &format!("{}.material1", field))?,
+                material2.build(context, &format!("{}.material2", field))?,
+                scale,
+            )
+            .into()),
+            TextureDescription::Image {
+                path,
+                filter,
+                addressing,
+                parameter,
+                srgb,
+                scale,
+                texture,
+                material,
+            } => {
+                let path = context.base_dir.join(path);
+                let srgb = srgb.unwrap_or(matches!(parameter, TextureParameterDescription::Color));
+                let cached = context.images.borrow().get(&(path.clone(), srgb)).cloned();
+                let image = match cached {
+                    Some(image) => image,
+                    None => {
+                        let image = TextureImage::load(&path, srgb).map_err(|source| {
+                            SceneError::Image {
+                                field: format!("{}.path", field),
+                                path: path.clone(),
+                                source,
+                            }
+                        })?;
+                        let image = Arc::new(image);
+                        context
+                            .images
+                            .borrow_mut()
+                            .insert((path, srgb), image.clone());
+                        image
+                    }
+                };
+                let base = surface(texture.map(|texture| *texture), material, context, field)?;
+
+                Ok(ImageTexture::new(image, base)
+                    .with_filter(match filter {
+                        TextureFilterDescription::Nearest => TextureFilter::Nearest,
+                        TextureFilterDescription::Bilinear => TextureFilter::Bilinear,
+                        TextureFilterDescription::Bicubic => TextureFilter::Bicubic,
+                    })
+                    .with_addressing(match addressing {
+                        AddressingDescription::Wrap => Addressing::Wrap,
+                        AddressingDescription::Clamp => Addressing::Clamp,
+                        AddressingDescription::Mirror => Addressing::Mirror,
+                    })
+                    .with_parameter(match parameter {
+                        TextureParameterDescription::Color => TextureParameter::Color,
+                        TextureParameterDescription::Ambient => TextureParameter::Ambient,
+                        TextureParameterDescription::Diffuse => TextureParameter::Diffuse,
+                        TextureParameterDescription::Specular => TextureParameter::Specular,
+                        TextureParameterDescription::Reflection => TextureParameter::Reflection,
+                        TextureParameterDescription::Transparency => TextureParameter::Transparency,
+                    })
+                    .with_scale(scale)
+                    .into())
+            }
+        }
+    }
+}
+
+/// Texture of an object given either a `texture` or a `material` field.
+fn surface(
+    texture: Option<TextureDescription>,
+    material: Option<MaterialRef>,
+    context: &Context,
+    field: &str,
+) -> Result<Texture, SceneError> {
+    match (texture, material) {
+        (Some(_), Some(_)) => Err(SceneError::invalid(
+            field,
+            "`texture` and `material` are mutually exclusive",
+        )),
+        (Some(texture), None) => texture.build(context, &format!("{}.texture", field)),
+        (None, Some(material)) => Ok(Texture::new_uniform(
+            material.build(context, &format!("{}.material", field))?,
+        )),
+        (None, None) => Ok(Texture::default()),
+    }
+}
+
+impl ObjectDescription {
+    fn build(self, context: &Context, field: &str) -> Result<Object, SceneError> {
+        match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                texture,
+                material,
+            } => {
+                if radius <= 0.0 {
+                    return Err(SceneError::invalid(
+                        &format!("{}.radius", field),
+                        "radius must be positive",
+                    ));
+                }
+                Ok(Sphere::new(
+                    center.into(),
+                    radius,
+                    surface(texture, material, context, field)?,
+                )
+                .into())
+            }
+            ObjectDescription::Plane {
+                position,
+                normal,
+                scale,
+                texture,
+                material,
+            } => {
+                if scale <= 0.0 {
+                    return Err(SceneError::invalid(
+                        &format!("{}.scale", field),
+                        "scale must be positive",
+                    ));
+                }
+                Ok((&Plane::new(
+                    position.into(),
+                    vector(&format!("{}.normal", field), normal)?,
+                    surface(texture, material, context, field)?,
+                    scale,
+                ))
+                    .into())
+            }
+            ObjectDescription::Triangle {
+                points,
+                uvs,
+                texture,
+                material,
+            } => Ok(Triangle {
+                points: points.map(Point::from),
+                uvs: uvs.map(|uvs| uvs.map(|[u, v]| (u, v))),
+                texture: surface(texture, material, context, field)?,
+            }
+            .into()),
+            ObjectDescription::SmoothTriangle {
+                points,
+                normals,
+                uvs,
+                texture,
+                material,
+            } => {
+                let mut vectors = [Vector::zero(); 3];
+                for (i, normal) in normals.into_iter().enumerate() {
+                    vectors[i] = vector(&format!("{}.normals[{}]", field, i), normal)?;
+                }
+                let mut triangle = SmoothTriangle::new(
+                    points.map(Point::from),
+                    vectors,
+                    surface(texture, material, context, field)?,
+                );
+                triangle.uvs = uvs.map(|uvs| uvs.map(|[u, v]| (u, v)));
+                Ok(triangle.into())
+            }
+            ObjectDescription::Mesh { faces } => {
+                let faces = faces
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, face)| {
+                        let field = format!("{}.faces[{}]", field, i);
+                        match face.build(context, &field)? {
+                            Object::Sphere(_) | Object::Plane(_) => Err(SceneError::invalid(
+                                &field,
+                                "mesh faces must be triangles or meshes",
+                            )),
+                            face => Ok(face),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((&Mesh::from_objects(&faces)).into())
+            }
+            ObjectDescription::Obj {
+                path,
+                texture,
+                material,
+            } => {
+                let mesh = obj::load_mesh(&context.base_dir.join(path)).map_err(|source| {
+                    SceneError::Obj {
+                        field: format!("{}.path", field),
+                        source,
+                    }
+                })?;
+                if mesh.faces.is_empty() {
+                    return Err(SceneError::invalid(
+                        &format!("{}.path", field),
+                        "the file contains no face",
+                    ));
+                }
+                let mesh = if texture.is_some() || material.is_some() {
+                    mesh.with_texture(surface(texture, material, context, field)?)
+                } else {
+                    mesh
+                };
+                Ok((&mesh).into())
+            }
+            ObjectDescription::Instance { object, transform } => {
+                let object = object.build(context, &format!("{}.object", field))?;
+                let transform = transform.into_iter().enumerate().try_fold(
+                    Transform::identity(),
+                    |acc, (i, step)| {
+                        let step = step.build(&format!("{}.transform[{}]", field, i))?;
+                        Ok::<_, SceneError>(acc.then(&step))
+                    },
+                )?;
+                Ok(Instance::new(object, transform).into())
+            }
+            ObjectDescription::Metaball {
+                bounds,
+                resolution,
+                fronteer,
+                points,
+                texture,
+                material,
+            } => {
+                if resolution <= 0.0 {
+                    return Err(SceneError::invalid(
+                        &format!("{}.resolution", field),
+                        "resolution must be positive",
+                    ));
+                }
+                if (0..3).any(|axis| bounds[0][axis] >= bounds[1][axis]) {
+                    return Err(SceneError::invalid(
+                        &format!("{}.bounds", field),
+                        "the first corner must be below the second one on every axis",
+                    ));
+                }
+                if fronteer <= 0.0 {
+                    return Err(SceneError::invalid(
+                        &format!("{}.fronteer", field),
+                        "fronteer must be positive",
+                    ));
+                }
+                let points = points
+                    .into_iter()
+                    .map(|point| MetaballPoint::new(point.position.into(), point.radius))
+                    .collect();
+                Ok(Metaball::new(
+                    (bounds[0].into(), bounds[1].into()),
+                    resolution,
+                    points,
+                    surface(texture, material, context, field)?,
+                    fronteer,
+                )
+                .into())
+            }
+        }
+    }
+}
+
+impl ProjectionDescription {
+    fn build(self, field: &str) -> Result<Projection, SceneError> {
+        let angle = |fov: f64, max: f64| {
+            if fov > 0.0 && fov <= max {
+                Ok(fov)
+            } else {
+                Err(SceneError::invalid(
+                    &format!("{}.fov", field),
+                    format!("field of view must be between 0 and {} degrees", max),
+                ))
+            }
+        };
+        Ok(match self {
+            ProjectionDescription::Perspective => Projection::Perspective,
+            ProjectionDescription::Orthographic { width } => {
+                if width <= 0.0 {
+                    return Err(SceneError::invalid(
+                        &format!("{}.width", field),
+                        "width must be positive",
+                    ));
+                }
+                Projection::Orthographic { width }
+            }
+            ProjectionDescription::FisheyeEquidistant { fov } => Projection::FisheyeEquidistant {
+                fov: angle(fov, 360.0)?,
+            },
+            ProjectionDescription::FisheyeEquisolid { fov } => Projection::FisheyeEquisolid {
+                fov: angle(fov, 360.0)?,
+            },
+            ProjectionDescription::Cylindrical { fov } => Projection::Cylindrical {
+                fov: angle(fov, 360.0)?,
+            },
+            ProjectionDescription::Equirectangular => Projection::Equirectangular,
+        })
+    }
+}
+
+impl AdaptiveDescription {
+    fn build(self, field: &str) -> Result<AdaptiveSampling, SceneError> {
+        if self.threshold <= 0.0 {
+            return Err(SceneError::invalid(
+                &format!("{}.threshold", field),
+                "threshold must be positive",
+            ));
+        }
+        let mut adaptive = AdaptiveSampling::new(self.threshold);
+        if let Some(min_samples) = self.min_samples {
+            if min_samples < 2 {
+                return Err(SceneError::invalid(
+                    &format!("{}.min_samples", field),
+                    "at least 2 samples are needed to estimate the noise",
+                ));
+            }
+            adaptive = adaptive.with_min_samples(min_samples);
+        }
+        if let Some(max_samples) = self.max_samples {
+            adaptive = adaptive.with_max_samples(max_samples);
+        }
+        if adaptive.max_samples < adaptive.min_samples {
+            return Err(SceneError::invalid(
+                &format!("{}.max_samples", field),
+                format!(
+                    "max_samples must be at least min_samples ({})",
+                    adaptive.min_samples
+                ),
+            ));
+        }
+        Ok(adaptive)
+    }
+}
+
+impl FilterDescription {
+    fn build(self, field: &str) -> Result<Filter, SceneError> {
+        let positive = |name: &str, value: Option<f64>, default: f64| match value {
+            Some(value) if value <= 0.0 => Err(SceneError::invalid(
+                &format!("{}.{}", field, name),
+                format!("{} must be positive", name),
+            )),
+            Some(value) => Ok(value),
+            None => Ok(default),
+        };
+        Ok(match self {
+            FilterDescription::Box { radius } => Filter::Box {
+                radius: positive("radius", radius, 0.5)?,
+            },
+            FilterDescription::Tent { radius } => Filter::Tent {
+                radius: positive("radius", radius, 1.0)?,
+            },
+            FilterDescription::Gaussian { radius, sigma } => Filter::Gaussian {
+                radius: positive("radius", radius, 1.5)?,
+                sigma: positive("sigma", sigma, 0.5)?,
+            },
+            FilterDescription::Mitchell { radius, b, c } => Filter::Mitchell {
+                radius: positive("radius", radius, 2.0)?,
+                b: b.unwrap_or(1.0 / 3.0),
+                c: c.unwrap_or(1.0 / 3.0),
+            },
+            FilterDescription::Lanczos { radius } => Filter::Lanczos {
+                radius: positive("radius", radius, 3.0)?,
+            },
+        })
+    }
+}
+
+impl EnvironmentDescription {
+    fn build(self, base_dir: &Path, field: &str) -> Result<Light, SceneError> {
+        let path = base_dir.join(&self.path);
+        let map = EnvironmentMap::load(&path).map_err(|source| SceneError::Image {
+            field: format!("{}.path", field),
+            path,
+            source,
+        })?;
+        let mut light = EnvironmentLight::new(Arc::new(map), 1.0, 0.0);
+        if let Some(intensity) = self.intensity {
+            light = light.with_intensity(intensity);
+        }
+        if let Some(rotation) = self.rotation {
+            light = light.with_rotation(rotation);
+        }
+        if let Some(up) = self.up {
+            light = light.with_up(vector(&format!("{}.up", field), up)?);
+        }
+        let samples = light_samples(field, self.samples, light.samples)?;
+        Ok(light.with_samples(samples).into())
+    }
+}
+
+impl LightDescription {
+    fn build(self, field: &str) -> Result<Light, SceneError> {
+        let direction_field = format!("{}.direction", field);
+        match self {
+            LightDescription::Directional {
+                direction,
+                color,
+                intensity,
+                angular_diameter,
+                samples,
+            } => {
+                let mut light = DirectionalLight::default()
+                    .with_direction(vector(&direction_field, direction)?);
+                if let Some(color) = color {
+                    light = light.with_color(color.0);
+                }
+                if let Some(intensity) = intensity {
+                    light = light.with_intensity(intensity);
+                }
+                if let Some(angular_diameter) = angular_diameter {
+                    if !(0.0..180.0).contains(&angular_diameter) {
+                        return Err(SceneError::invalid(
+                            &format!("{}.angular_diameter", field),
+                            "angular diameter must be between 0 and 180 degrees",
+                        ));
+                    }
+                    light = light.with_angular_diameter(angular_diameter);
+                }
+                let samples = light_samples(field, samples, light.samples)?;
+                Ok(light.with_samples(samples).into())
+            }
+            LightDescription::Point {
+                position,
+                color,
+                intensity,
+            } => {
+                let mut light = PointLight::default().with_position(position.into());
+                if let Some(color) = color {
+                    light = light.with_color(color.0);
+                }
+                if let Some(intensity) = intensity {
+                    light = light.with_intensity(intensity);
+                }
+                Ok(light.into())
+            }
+            LightDescription::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                cutoff,
+                falloff,
+            } => {
+                let mut light = SpotLight::default()
+                    .with_position(position.into())
+                    .with_direction(vector(&direction_field, direction)?);
+                if let Some(color) = color {
+                    light = light.with_color(color.0);
+                }
+                if let Some(intensity) = intensity {
+                    light = light.with_intensity(intensity);
+                }
+                if let Some(cutoff) = cutoff {
+                    light = light.with_cutoff(cutoff);
+                }
+                if let Some(falloff) = falloff {
+                    light = light.with_falloff(falloff);
+                }
+                Ok(light.into())
+            }
+            LightDescription::Rect {
+                position,
+                u,
+                v,
+                color,
+                intensity,
+                samples,
+            } => {
+                let (u, v) = (Vector::from(u), Vector::from(v));
+                if u.cross(&v).length_squared() == 0.0 {
+                    return Err(SceneError::invalid(
+                        &format!("{}.v", field),
+                        "edges must not be zero or parallel",
+                    ));
+                }
+                let mut light = RectLight::default()
+                    .with_position(position.into())
+                    .with_edges(u, v);
+                if let Some(color) = color {
+                    light = light.with_color(color.0);
+                }
+                if let Some(intensity) = intensity {
+                    light = light.with_intensity(intensity);
+                }
+                let samples = light_samples(field, samples, light.samples)?;
+                Ok(light.with_samples(samples).into())
+            }
+            LightDescription::Disk {
+                position,
+                normal,
+                radius,
+                color,
+                intensity,
+                samples,
+            } => {
+                let mut light = DiskLight::default()
+                    .with_position(position.into())
+                    .with_normal(vector(&format!("{}.normal", field), normal)?)
+                    .with_radius(light_radius(field, radius)?);
+                if let Some(color) = color {
+                    light = light.with_color(color.0);
+                }
+                if let Some(intensity) = intensity {
+                    light = light.with_intensity(intensity);
+                }
+                let samples = light_samples(field, samples, light.samples)?;
+                Ok(light.with_samples(samples).into())
+            }
+            LightDescription::Sphere {
+                position,
+                radius,
+                color,
+                intensity,
+                samples,
+            } => {
+                let mut light = SphereLight::default()
+                    .with_position(position.into())
+                    .with_radius(light_radius(field, radius)?);
+                if let Some(color) = color {
+                    light = light.with_color(color.0);
+                }
+                if let Some(intensity) = intensity {
+                    light = light.with_intensity(intensity);
+                }
+                let samples = light_samples(field, samples, light.samples)?;
+                Ok(light.with_samples(samples).into())
+            }
+        }
+    }
+}
+
+/// A color written either as `"#RRGGBB"` or as a linear `[r, g, b]` array.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorValue(pub Color);
+
+/// A single number, or a color as accepted by `ColorValue`.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorOrFloatValue(pub ColorOrFloat);
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a \"#RRGGBB\" string or an [r, g, b] array")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Color, E> {
+        Color::try_from_hex(value)
+            .ok_or_else(|| E::custom(format!("invalid hex color \"{}\"", value)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Color, A::Error> {
+        let [r, g, b] = <[f64; 3]>::deserialize(de::value::SeqAccessDeserializer::new(seq))?;
+        Ok(Color::new(r, g, b))
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ColorVisitor).map(ColorValue)
+    }
+}
+
+struct ColorOrFloatVisitor;
+
+impl<'de> Visitor<'de> for ColorOrFloatVisitor {
+    type Value = ColorOrFloat;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a number, a \"#RRGGBB\" string or an [r, g, b] array")
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<ColorOrFloat, E> {
+        Ok(ColorOrFloat::Float(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<ColorOrFloat, E> {
+        Ok(ColorOrFloat::Float(value as f64))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<ColorOrFloat, E> {
+        Ok(ColorOrFloat::Float(value as f64))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<ColorOrFloat, E> {
+        ColorVisitor.visit_str(value).map(ColorOrFloat::Color)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<ColorOrFloat, A::Error> {
+        ColorVisitor.visit_seq(seq).map(ColorOrFloat::Color)
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorOrFloatValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(ColorOrFloatVisitor)
+            .map(ColorOrFloatValue)
+    }
+}
+
+struct MaterialRefVisitor;
+
+impl<'de> Visitor<'de> for MaterialRefVisitor {
+    type Value = MaterialRef;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a material name or a material object")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<MaterialRef, E> {
+        Ok(MaterialRef::Named(value.to_string()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<MaterialRef, A::Error> {
+        MaterialDescription::deserialize(de::value::MapAccessDeserializer::new(map))
+            .map(|material| MaterialRef::Inline(Box::new(material)))
+    }
+}
+
+impl<'de> Deserialize<'de> for MaterialRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MaterialRefVisitor)
+    }
+}
+
+struct ObjectRefVisitor;
+
+impl<'de> Visitor<'de> for ObjectRefVisitor {
+    type Value = ObjectRef;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a geometry name or an object")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<ObjectRef, E> {
+        Ok(ObjectRef::Named(value.to_string()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<ObjectRef, A::Error> {
+        ObjectDescription::deserialize(de::value::MapAccessDeserializer::new(map))
+            .map(|object| ObjectRef::Inline(Box::new(object)))
+    }
+}
+
+impl<'de> Deserialize<'de> for ObjectRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ObjectRefVisitor)
+    }
+}
@@ -1,51 +1,576 @@
-use image::RgbImage;
+use std::{path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
 use rusttracer::{
-    engine::{camera::Camera, scene::Scene},
-    generators::metaball::{Metaball, MetaballPoint},
-    light::point::PointLight,
+    engine::{
+        aov::Aov,
+        checkpoint::Checkpoint,
+        denoiser::Denoiser,
+        filter::Filter,
+        renderer::{RenderEvent, Renderer},
+        sampler::Sampler,
+        scene::Scene,
+        tone_mapping::{ToneMapper, ToneMapping},
+    },
     objects::Object,
 };
 
-fn main() {
-    let mut image = RgbImage::new(500, 500);
-    let scene = Scene::default()
-        .with_camera(Camera::default().with_anti_aliasing(100))
-        .with_background("#1CB5E0".into())
-        .with_object(
-            Metaball::default()
-                .with_bounds(((-10.0, -10.0, -10.0).into(), (10.0, 10.0, 10.0).into()))
-                .with_point(MetaballPoint::default().with_position((5.0, 0.0, 0.0).into()))
-                //.with_point(MetaballPoint::default().with_position((5.0, 0.0, -1.0).into()))
-                .with_resolution(0.4)
-                .with_fronteer(1.5)
-                .into(),
-        )
-        .with_light(
-            PointLight::default()
-                .with_position((5.0, 10.0, 10.0).into())
-                .with_color("#00FF00".into())
-                .with_intensity(10.0)
-                .into(),
-        )
-        .with_light(
-            PointLight::default()
-                .with_position((5.0, 10.0, -10.0).into())
-                .with_color("#FF0000".into())
-                .with_intensity(10.0)
-                .into(),
+#[derive(Debug, Parser)]
+#[command(name = "rusttracer", version, about = "A simple raytracer")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+// Parsed once, the size of the render options doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Render a scene file to an image
+    Render {
+        /// JSON scene file
+        scene: PathBuf,
+        /// Output image, the format is deduced from the extension. `exr`, `hdr` and `pfm` files
+        /// keep the unclamped linear colors
+        #[arg(short, long, default_value = "image.png")]
+        output: PathBuf,
+        #[arg(long, default_value_t = 500)]
+        width: u32,
+        #[arg(long, default_value_t = 500)]
+        height: u32,
+        /// Samples per pixel, overrides the camera's anti-aliasing setting
+        #[arg(long)]
+        spp: Option<u32>,
+        /// Sample pattern, overrides the camera's sampler
+        #[arg(long, value_enum)]
+        sampler: Option<SamplerKind>,
+        /// Seed of the samples, overrides the camera's seed. Renders with the same seed are
+        /// identical
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Reconstruction filter, overrides the camera's filter
+        #[arg(long, value_enum)]
+        filter: Option<FilterKind>,
+        /// Radius of the filter in pixels, each filter has its own default
+        #[arg(long)]
+        filter_radius: Option<f64>,
+        /// Sample adaptively until the relative noise of every pixel is below this threshold,
+        /// overrides the camera's adaptive sampling
+        #[arg(long)]
+        noise_threshold: Option<f64>,
+        /// Samples taken in every pixel with adaptive sampling
+        #[arg(long)]
+        min_spp: Option<u32>,
+        /// Most samples taken in a pixel with adaptive sampling
+        #[arg(long)]
+        max_spp: Option<u32>,
+        /// Also write an image of the number of samples taken in every pixel
+        #[arg(long)]
+        heatmap: Option<PathBuf>,
+        /// Extra outputs, comma separated. They are layers of `exr` outputs and separate
+        /// `<output>.<aov>.<ext>` images otherwise
+        #[arg(long, value_enum, value_delimiter = ',')]
+        aov: Vec<AovKind>,
+        /// Denoise the image, guided by the albedo, normal and depth AOVs
+        #[arg(long)]
+        denoise: bool,
+        /// Blend between the noisy (0) and the denoised (1) image
+        #[arg(long, default_value_t = 1.0, requires = "denoise")]
+        denoise_strength: f64,
+        /// Iterations of the denoiser (1 to 10), each one doubles the size of the filter
+        #[arg(long, default_value_t = 5, requires = "denoise")]
+        denoise_iterations: u32,
+        /// Number of worker threads, defaults to the number of cores
+        #[arg(long)]
+        threads: Option<usize>,
+        /// Render progressively, adding this many samples to every pixel per pass
+        #[arg(long)]
+        samples_per_pass: Option<u32>,
+        /// Stop starting new passes after this many seconds, the first pass always completes.
+        /// Renders one sample per pass unless `--samples-per-pass` is given
+        #[arg(long)]
+        time_budget: Option<f64>,
+        /// Save the accumulated samples to this file while rendering. Renders one sample per pass
+        /// unless `--samples-per-pass` is given
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+        /// Seconds between two checkpoints, one is always saved at the end
+        #[arg(long, default_value_t = 60.0)]
+        checkpoint_interval: f64,
+        /// Continue from the checkpoint file if it exists. Raise `--spp` to add samples to a
+        /// finished render
+        #[arg(long, requires = "checkpoint")]
+        resume: bool,
+        /// Tone mapping curve used for 8-bit outputs
+        #[arg(long, value_enum, default_value_t = ToneMap::Clamp)]
+        tone_map: ToneMap,
+        /// Exposure compensation in stops (EV)
+        #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        exposure: f64,
+        /// Expose for the log-average luminance of the image, before `--exposure`
+        #[arg(long)]
+        auto_exposure: bool,
+        /// Exposed luminance displayed as white, defaults depend on the curve
+        #[arg(long)]
+        white_point: Option<f64>,
+    },
+    /// Export the triangles of a scene to a Wavefront OBJ file
+    ExportMesh {
+        /// JSON scene file
+        scene: PathBuf,
+        #[arg(short, long, default_value = "mesh.obj")]
+        output: PathBuf,
+        /// Index of the object to export, all meshes and triangles are exported by default
+        #[arg(long)]
+        object: Option<usize>,
+    },
+    /// Print a summary of a scene file
+    Info {
+        /// JSON scene file
+        scene: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SamplerKind {
+    Random,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl From<SamplerKind> for Sampler {
+    fn from(kind: SamplerKind) -> Sampler {
+        match kind {
+            SamplerKind::Random => Sampler::new_random(0),
+            SamplerKind::Stratified => Sampler::new_stratified(0),
+            SamplerKind::Halton => Sampler::new_halton(0),
+            SamplerKind::Sobol => Sampler::new_sobol(0),
+            SamplerKind::BlueNoise => Sampler::new_blue_noise(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl From<FilterKind> for Filter {
+    fn from(kind: FilterKind) -> Filter {
+        match kind {
+            FilterKind::Box => Filter::new_box(),
+            FilterKind::Tent => Filter::new_tent(),
+            FilterKind::Gaussian => Filter::new_gaussian(),
+            FilterKind::Mitchell => Filter::new_mitchell(),
+            FilterKind::Lanczos => Filter::new_lanczos(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AovKind {
+    Depth,
+    Normal,
+    ShadingNormal,
+    Albedo,
+    Uv,
+    ObjectId,
+    MaterialId,
+    Direct,
+    Indirect,
+    Specular,
+}
+
+impl From<AovKind> for Aov {
+    fn from(kind: AovKind) -> Aov {
+        match kind {
+            AovKind::Depth => Aov::Depth,
+            AovKind::Normal => Aov::Normal,
+            AovKind::ShadingNormal => Aov::ShadingNormal,
+            AovKind::Albedo => Aov::Albedo,
+            AovKind::Uv => Aov::Uv,
+            AovKind::ObjectId => Aov::ObjectId,
+            AovKind::MaterialId => Aov::MaterialId,
+            AovKind::Direct => Aov::Direct,
+            AovKind::Indirect => Aov::Indirect,
+            AovKind::Specular => Aov::Specular,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ToneMap {
+    Clamp,
+    Reinhard,
+    ExtendedReinhard,
+    Aces,
+    Uncharted2,
+}
+
+impl From<ToneMap> for ToneMapping {
+    fn from(tone_map: ToneMap) -> ToneMapping {
+        match tone_map {
+            ToneMap::Clamp => ToneMapping::Clamp,
+            ToneMap::Reinhard => ToneMapping::Reinhard,
+            ToneMap::ExtendedReinhard => ToneMapping::ExtendedReinhard,
+            ToneMap::Aces => ToneMapping::Aces,
+            ToneMap::Uncharted2 => ToneMapping::Uncharted2,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    match cli.command {
+        Command::Render {
+            scene,
+            output,
+            width,
+            height,
+            spp,
+            sampler,
+            seed,
+            filter,
+            filter_radius,
+            noise_threshold,
+            min_spp,
+            max_spp,
+            heatmap,
+            aov,
+            denoise,
+            denoise_strength,
+            denoise_iterations,
+            threads,
+            samples_per_pass,
+            time_budget,
+            checkpoint,
+            checkpoint_interval,
+            resume,
+            tone_map,
+            exposure,
+            auto_exposure,
+            white_point,
+        } => {
+            if let Some(threads) = threads {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build_global()?;
+            }
+
+            let mut scene = Scene::from_file(&scene)?;
+            if let Some(spp) = spp {
+                scene.camera = scene.camera.with_anti_aliasing(spp);
+            }
+            if let Some(sampler) = sampler {
+                let seed = scene.camera.sampler.seed();
+                scene.camera = scene
+                    .camera
+                    .with_sampler(Sampler::from(sampler).with_seed(seed));
+            }
+            if let Some(seed) = seed {
+                scene.camera = scene
+                    .camera
+                    .with_sampler(scene.camera.sampler.with_seed(seed));
+            }
+            if let Some(filter) = filter {
+                scene.camera = scene.camera.with_filter(filter.into());
+            }
+            if let Some(radius) = filter_radius {
+                if radius <= 0.0 {
+                    return Err("the filter radius must be positive".into());
+                }
+                scene.camera = scene
+                    .camera
+                    .with_filter(scene.camera.filter.with_radius(radius));
+            }
+            if noise_threshold.is_some() || min_spp.is_some() || max_spp.is_some() {
+                let mut adaptive = scene.camera.adaptive.unwrap_or_default();
+                if let Some(threshold) = noise_threshold {
+                    if threshold <= 0.0 {
+                        return Err("the noise threshold must be positive".into());
+                    }
+                    adaptive = adaptive.with_threshold(threshold);
+                }
+                if let Some(min_spp) = min_spp {
+                    if min_spp < 2 {
+                        return Err("at least 2 samples are needed to estimate the noise".into());
+                    }
+                    adaptive = adaptive.with_min_samples(min_spp);
+                }
+                if let Some(max_spp) = max_spp {
+                    adaptive = adaptive.with_max_samples(max_spp);
+                }
+                if adaptive.max_samples < adaptive.min_samples {
+                    return Err("--max-spp must be at least --min-spp".into());
+                }
+                scene.camera = scene.camera.with_adaptive(adaptive);
+            }
+
+            let mut tone_mapper = ToneMapper::new(tone_map.into())
+                .with_exposure(exposure)
+                .with_auto_exposure(auto_exposure);
+            if let Some(white_point) = white_point {
+                if white_point <= 0.0 {
+                    return Err("the white point must be positive".into());
+                }
+                tone_mapper = tone_mapper.with_white_point(white_point);
+            }
+
+            let mut renderer = Renderer::new(&scene);
+            let progressive = checkpoint.is_some() || time_budget.is_some();
+            if let Some(samples_per_pass) = samples_per_pass.or(progressive.then_some(1)) {
+                renderer = renderer.with_samples_per_pass(samples_per_pass);
+            }
+            if let Some(seconds) = time_budget {
+                if seconds <= 0.0 {
+                    return Err("the time budget must be positive".into());
+                }
+                renderer = renderer.with_time_budget(Duration::from_secs_f64(seconds));
+            }
+            let aovs = aov.into_iter().map(Aov::from).collect::<Vec<_>>();
+            let mut guides = aovs.clone();
+            if denoise {
+                if !(0.0..=1.0).contains(&denoise_strength) {
+                    return Err("the denoise strength must be between 0 and 1".into());
+                }
+                // Past 10 iterations the taps are over 1000 pixels apart
+                if !(1..=10).contains(&denoise_iterations) {
+                    return Err("the denoise iterations must be between 1 and 10".into());
+                }
+                for guide in [Aov::Albedo, Aov::ShadingNormal, Aov::Depth] {
+                    if !guides.contains(&guide) {
+                        guides.push(guide);
+                    }
+                }
+            }
+            if !guides.is_empty() {
+                renderer = renderer.with_aovs(&guides);
+            }
+            let mut resumed = None;
+            if let Some(path) = &checkpoint {
+                if checkpoint_interval < 0.0 {
+                    return Err("the checkpoint interval cannot be negative".into());
+                }
+                renderer =
+                    renderer.with_checkpoint(path, Duration::from_secs_f64(checkpoint_interval));
+                if resume && path.exists() {
+                    let checkpoint = Checkpoint::load(path)?;
+                    if (checkpoint.width(), checkpoint.height()) != (width, height) {
+                        return Err(format!(
+                            "the checkpoint is {}x{}, not {}x{}",
+                            checkpoint.width(),
+                            checkpoint.height(),
+                            width,
+                            height
+                        )
+                        .into());
+                    }
+                    resumed = Some(checkpoint);
+                }
+            }
+
+            let bar = ProgressBar::new(0).with_style(
+                ProgressStyle::with_template("{msg} [{elapsed_precise}] {wide_bar} {pos}/{len}")
+                    .unwrap(),
+            );
+            let progress = bar.clone();
+            let renderer = renderer.with_callback(move |event| match event {
+                RenderEvent::TileDone {
+                    pass, done, total, ..
+                } => {
+                    progress.set_length(total as u64);
+                    progress.set_position(done as u64);
+                    progress.set_message(format!("pass {}", pass + 1));
+                }
+                RenderEvent::Checkpoint {
+                    path,
+                    result: Err(error),
+                } => {
+                    progress.println(format!(
+                        "warning: cannot save the checkpoint {}: {}",
+                        path.display(),
+                        error
+                    ));
+                }
+                _ => {}
+            });
+            let mut film = match resumed {
+                Some(checkpoint) => renderer.resume(checkpoint)?,
+                None => renderer.render(width, height),
+            };
+            bar.finish_and_clear();
+
+            if denoise {
+                film = Denoiser::new(denoise_strength)
+                    .with_iterations(denoise_iterations)
+                    .denoise(&film);
+                // The guides are only saved when asked for
+                film.aovs.retain(|aov, _| aovs.contains(aov));
+            }
+            film.save(&output, &tone_mapper)?;
+            if let Some(heatmap) = heatmap {
+                film.sample_heatmap().save(heatmap)?;
+            }
+        }
+        Command::ExportMesh {
+            scene,
+            output,
+            object,
+        } => {
+            let scene = Scene::from_file(&scene)?;
+            let objects = match object {
+                Some(index) => {
+                    let object = scene.objects.get(index).ok_or_else(|| {
+                        format!(
+                            "object {} does not exist, the scene has {} objects",
+                            index,
+                            scene.objects.len()
+                        )
+                    })?;
+                    if !is_exportable(object) {
+                        return Err(format!(
+                            "object {} is a {}, only meshes and triangles can be exported",
+                            index,
+                            kind(object)
+                        )
+                        .into());
+                    }
+                    vec![object]
+                }
+                None => scene.objects.iter().filter(|o| is_exportable(o)).collect(),
+            };
+            if objects.is_empty() {
+                return Err("the scene has no mesh or triangle to export".into());
+            }
+
+            let mut file = std::fs::File::create(&output)?;
+            for object in objects {
+                match object {
+                    Object::Mesh(mesh) => mesh.write_to_obj(&mut file),
+                    Object::Triangle(triangle) => triangle.write_to_obj(&mut file),
+                    Object::SmoothTriangle(triangle) => triangle.write_to_obj(&mut file),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        Command::Info { scene } => {
+            let scene = Scene::from_file(&scene)?;
+            print_info(&scene);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_exportable(object: &Object) -> bool {
+    matches!(
+        object,
+        Object::Mesh(_) | Object::Triangle(_) | Object::SmoothTriangle(_)
+    )
+}
+
+fn kind(object: &Object) -> &'static str {
+    match object {
+        Object::Sphere(_) => "sphere",
+        Object::Plane(_) => "plane",
+        Object::Triangle(_) => "triangle",
+        Object::SmoothTriangle(_) => "smooth triangle",
+        Object::Mesh(_) => "mesh",
+        Object::Instance(_) => "instance",
+    }
+}
+
+fn sampler_name(sampler: &Sampler) -> &'static str {
+    match sampler {
+        Sampler::Random(_) => "random",
+        Sampler::Stratified(_) => "stratified",
+        Sampler::Halton(_) => "halton",
+        Sampler::Sobol(_) => "sobol",
+        Sampler::BlueNoise(_) => "blue noise",
+    }
+}
+
+fn triangle_count(object: &Object) -> usize {
+    match object {
+        Object::Triangle(_) | Object::SmoothTriangle(_) => 1,
+        Object::Mesh(mesh) => mesh.faces.len(),
+        Object::Instance(instance) => triangle_count(&instance.object),
+        _ => 0,
+    }
+}
+
+fn print_info(scene: &Scene) {
+    let camera = &scene.camera;
+    println!("Camera");
+    println!("  position: {:?}", <(f64, f64, f64)>::from(camera.position));
+    println!(
+        "  direction: {:?}",
+        <(f64, f64, f64)>::from(camera.direction)
+    );
+    println!("  projection: {:?}", camera.projection);
+    println!("  integrator: {:?}", camera.integrator);
+    println!("  max bounces: {}", camera.max_bounces);
+    println!("  anti-aliasing: {}", camera.anti_aliasing);
+    println!("  filter: {:?}", camera.filter);
+    if let Some(adaptive) = camera.adaptive {
+        println!(
+            "  adaptive sampling: noise threshold {} ({} to {} samples)",
+            adaptive.threshold, adaptive.min_samples, adaptive.max_samples
         );
+    }
+    println!(
+        "  sampler: {} (seed {})",
+        sampler_name(&camera.sampler),
+        camera.sampler.seed()
+    );
+    if camera.aperture > 0.0 {
+        println!(
+            "  aperture: {} (focus distance {})",
+            camera.aperture, camera.focus_distance
+        );
+    }
+    println!("Background: {}", scene.background.to_hex());
 
-    match &scene.objects[0] {
-        Object::Mesh(mesh) => {
-            let mut file = std::fs::File::create("mesh.obj").unwrap();
-            mesh.write_to_obj(&mut file);
+    println!("Objects: {}", scene.objects.len());
+    for (index, object) in scene.objects.iter().enumerate() {
+        let triangles = triangle_count(object);
+        if triangles > 1 {
+            println!("  [{}] {} ({} triangles)", index, kind(object), triangles);
+        } else {
+            println!("  [{}] {}", index, kind(object));
         }
-        Object::Sphere(_) => todo!(),
-        Object::Plane(_) => todo!(),
-        Object::Triangle(_) => todo!(),
-        Object::SmoothTriangle(_) => todo!(),
     }
 
-    scene.render_into(&mut image);
-    image.save("image.png").unwrap();
+    println!("Lights: {}", scene.lights.len());
+    for (index, light) in scene.lights.iter().enumerate() {
+        println!("  [{}] {:?}", index, light);
+    }
+
+    let bounds = scene.bvh.bounding_box();
+    if !bounds.is_empty() {
+        println!(
+            "Bounds: {:?} to {:?}",
+            <(f64, f64, f64)>::from(bounds.min),
+            <(f64, f64, f64)>::from(bounds.max)
+        );
+    }
+    if !scene.bvh.unbounded.is_empty() {
+        println!("Unbounded objects: {}", scene.bvh.unbounded.len());
+    }
 }
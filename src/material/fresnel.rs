@@ -0,0 +1,40 @@
+/// Approximation used to split light between reflection and refraction at a dielectric interface.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fresnel {
+    /// Schlick's polynomial approximation.
+    Schlick,
+    /// Exact Fresnel equations for unpolarized light.
+    #[default]
+    Exact,
+}
+
+impl Fresnel {
+    /// Fraction of light reflected when going from a medium of index `eta_i` into one of index `eta_t`.
+    ///
+    /// `cos_i` is the cosine between the incident direction and the normal on the incident side.
+    /// Returns 1 on total internal reflection.
+    pub fn reflectance(&self, cos_i: f64, eta_i: f64, eta_t: f64) -> f64 {
+        let cos_i = cos_i.clamp(0.0, 1.0);
+        let sin_t = eta_i / eta_t * (1.0 - cos_i * cos_i).sqrt();
+        if sin_t >= 1.0 {
+            return 1.0;
+        }
+        let cos_t = (1.0 - sin_t * sin_t).sqrt();
+
+        match self {
+            Fresnel::Schlick => {
+                let r0 = ((eta_i - eta_t) / (eta_i + eta_t)).powi(2);
+                // Leaving the denser medium the transmitted angle is the larger one, so its cosine
+                // is used instead. Total internal reflection already returned above
+                let cos = if eta_i > eta_t { cos_t } else { cos_i };
+                r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+            }
+            Fresnel::Exact => {
+                let parallel = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+                let perpendicular =
+                    (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+                (parallel * parallel + perpendicular * perpendicular) / 2.0
+            }
+        }
+    }
+}
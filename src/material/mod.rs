@@ -1,8 +1,11 @@
 use crate::utils::{color::Color, color_or_float::ColorOrFloat};
 
+use self::fresnel::Fresnel;
+
+pub mod fresnel;
 pub mod texture;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Material {
     pub name: &'static str,
     pub color: Color,
@@ -13,9 +16,13 @@ pub struct Material {
     pub specular_exponent: f64,
     pub transparency: ColorOrFloat,
     pub refraction_index: f64,
+    /// Beer-Lambert density: light travelling one unit inside the object is tinted by `color` this many times.
+    pub absorption: f64,
+    pub fresnel: Fresnel,
 }
 
 impl Material {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: &'static str,
         color: Color,
@@ -37,6 +44,8 @@ impl Material {
             specular_exponent,
             transparency,
             refraction_index,
+            absorption: 0.0,
+            fresnel: Fresnel::default(),
         }
     }
 
@@ -82,6 +91,28 @@ impl Material {
         material.refraction_index = refraction_index;
         material
     }
+
+    pub fn with_absorption(&self, absorption: f64) -> Material {
+        let mut material = *self;
+        material.absorption = absorption;
+        material
+    }
+
+    pub fn with_fresnel(&self, fresnel: Fresnel) -> Material {
+        let mut material = *self;
+        material.fresnel = fresnel;
+        material
+    }
+
+    /// Fraction of light that survives travelling `distance` inside the material.
+    pub fn transmittance(&self, distance: f64) -> Color {
+        let exponent = distance * self.absorption;
+        Color::new(
+            self.color.r.powf(exponent),
+            self.color.g.powf(exponent),
+            self.color.b.powf(exponent),
+        )
+    }
 }
 
 impl Default for Material {
@@ -96,6 +127,8 @@ impl Default for Material {
             specular_exponent: 10.0,
             transparency: ColorOrFloat::Float(0.0),
             refraction_index: 1.5,
+            absorption: 0.0,
+            fresnel: Fresnel::default(),
         }
     }
 }
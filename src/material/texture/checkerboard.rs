@@ -2,7 +2,7 @@ use crate::material::Material;
 
 use super::{Texturable, Texture};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CheckerboardTexture {
     pub material1: Material,
     pub material2: Material,
@@ -40,7 +40,7 @@ impl CheckerboardTexture {
 }
 
 impl Texturable for CheckerboardTexture {
-    fn value(self, u: f64, v: f64) -> Material {
+    fn value(&self, u: f64, v: f64) -> Material {
         let sines = (self.scale * u).sin() * (self.scale * v).sin();
         if sines < 0.0 {
             self.material1
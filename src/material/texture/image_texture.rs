@@ -0,0 +1,285 @@
+use std::{fmt, fs::File, io::BufReader, path::Path, sync::Arc};
+
+use image::{codecs::hdr::HdrDecoder, DynamicImage, ImageError, ImageFormat};
+
+use crate::{engine::checkpoint::checksum, material::Material, utils::color::Color};
+
+use super::{Texturable, Texture};
+
+/// Texels of a picture, shared by every texture sampling it.
+#[derive(Clone, PartialEq)]
+pub struct TextureImage {
+    pub width: usize,
+    pub height: usize,
+    /// Linear colors, row by row from the top of the picture.
+    pub texels: Vec<Color>,
+    /// Hash of the texels, see `checksum`.
+    checksum: u64,
+}
+
+impl TextureImage {
+    pub fn new(width: usize, height: usize, texels: Vec<Color>) -> TextureImage {
+        assert_eq!(texels.len(), width * height, "texel count mismatch");
+        TextureImage {
+            width,
+            height,
+            checksum: checksum(&texels),
+            texels,
+        }
+    }
+
+    /// Loads a picture, HDR and EXR files are linear while other formats are decoded from sRGB
+    /// when `srgb` is set, and only divided by 255 otherwise for data like roughness maps.
+    pub fn load(path: impl AsRef<Path>, srgb: bool) -> Result<TextureImage, ImageError> {
+        let path = path.as_ref();
+        // The generic decoder converts Radiance files to 8-bit
+        if ImageFormat::from_path(path).ok() == Some(ImageFormat::Hdr) {
+            let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+            let (width, height) = (
+                decoder.metadata().width as usize,
+                decoder.metadata().height as usize,
+            );
+            let texels = decoder
+                .read_image_hdr()?
+                .into_iter()
+                .map(|pixel| Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64))
+                .collect();
+            return Ok(TextureImage::new(width, height, texels));
+        }
+
+        let image = image::open(path)?;
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let texels = match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => image
+                .into_rgb32f()
+                .pixels()
+                .map(|pixel| Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64))
+                .collect(),
+            _ => {
+                let decode = |value: u8| {
+                    let value = value as f64 / 255.0;
+                    if srgb {
+                        srgb_to_linear(value)
+                    } else {
+                        value
+                    }
+                };
+                image
+                    .into_rgb8()
+                    .pixels()
+                    .map(|pixel| Color::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2])))
+                    .collect()
+            }
+        };
+        Ok(TextureImage::new(width, height, texels))
+    }
+
+    /// Texel at the integer coordinates `x` and `y`, which may lie outside of the picture.
+    pub fn texel(&self, x: i64, y: i64, addressing: Addressing) -> Color {
+        let x = addressing.resolve(x, self.width);
+        let y = addressing.resolve(y, self.height);
+        self.texels[y * self.width + x]
+    }
+}
+
+// The texels would flood the scene dumps
+impl fmt::Debug for TextureImage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TextureImage")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("checksum", &self.checksum)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Inverse of the sRGB transfer function.
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Reconstruction of the picture between texel centers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// Closest texel, blocky when magnified.
+    Nearest,
+    /// Linear interpolation of the 2×2 closest texels.
+    #[default]
+    Bilinear,
+    /// Catmull-Rom interpolation of the 4×4 closest texels, smoother when magnified.
+    Bicubic,
+}
+
+/// Handling of the coordinates outside of the picture.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Addressing {
+    /// Tiles the picture.
+    #[default]
+    Wrap,
+    /// Repeats the texels of the border.
+    Clamp,
+    /// Tiles the picture, flipping every other copy so that the seams are invisible.
+    Mirror,
+}
+
+impl Addressing {
+    fn resolve(&self, coordinate: i64, size: usize) -> usize {
+        let size = size as i64;
+        (match self {
+            Addressing::Wrap => coordinate.rem_euclid(size),
+            Addressing::Clamp => coordinate.clamp(0, size - 1),
+            Addressing::Mirror => {
+                let coordinate = coordinate.rem_euclid(2 * size);
+                if coordinate < size {
+                    coordinate
+                } else {
+                    2 * size - 1 - coordinate
+                }
+            }
+        }) as usize
+    }
+}
+
+/// Material parameter driven by the picture.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextureParameter {
+    #[default]
+    Color,
+    Ambient,
+    Diffuse,
+    Specular,
+    Reflection,
+    Transparency,
+}
+
+/// Picture mapped on the texture coordinates, replacing one parameter of the material given by
+/// the `base` texture. `v` goes up, so the top of the picture is at `v = 1`.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    pub image: Arc<TextureImage>,
+    pub filter: TextureFilter,
+    pub addressing: Addressing,
+    pub parameter: TextureParameter,
+    /// Number of repetitions of the picture per unit of texture coordinates.
+    pub scale: f64,
+    pub base: Box<Texture>,
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<TextureImage>, base: Texture) -> ImageTexture {
+        ImageTexture {
+            image,
+            filter: TextureFilter::default(),
+            addressing: Addressing::default(),
+            parameter: TextureParameter::default(),
+            scale: 1.0,
+            base: Box::new(base),
+        }
+    }
+
+    pub fn with_filter(self, filter: TextureFilter) -> ImageTexture {
+        ImageTexture { filter, ..self }
+    }
+
+    pub fn with_addressing(self, addressing: Addressing) -> ImageTexture {
+        ImageTexture { addressing, ..self }
+    }
+
+    pub fn with_parameter(self, parameter: TextureParameter) -> ImageTexture {
+        ImageTexture { parameter, ..self }
+    }
+
+    pub fn with_scale(self, scale: f64) -> ImageTexture {
+        ImageTexture { scale, ..self }
+    }
+
+    pub fn with_base(self, base: Texture) -> ImageTexture {
+        ImageTexture {
+            base: Box::new(base),
+            ..self
+        }
+    }
+
+    /// Filtered color of the picture at the texture coordinates `u` and `v`.
+    pub fn sample(&self, u: f64, v: f64) -> Color {
+        let image = &self.image;
+        // Texel centers are at half-integer coordinates
+        let x = u * self.scale * image.width as f64 - 0.5;
+        let y = (1.0 - v * self.scale) * image.height as f64 - 0.5;
+        let texel = |x: i64, y: i64| image.texel(x, y, self.addressing);
+
+        match self.filter {
+            TextureFilter::Nearest => texel(x.round() as i64, y.round() as i64),
+            TextureFilter::Bilinear => {
+                let (left, top) = (x.floor(), y.floor());
+                let (tx, ty) = (x - left, y - top);
+                let (left, top) = (left as i64, top as i64);
+                let row = |y: i64| texel(left, y) * (1.0 - tx) + texel(left + 1, y) * tx;
+                row(top) * (1.0 - ty) + row(top + 1) * ty
+            }
+            TextureFilter::Bicubic => {
+                let (left, top) = (x.floor(), y.floor());
+                let (wx, wy) = (catmull_rom(x - left), catmull_rom(y - top));
+                let (left, top) = (left as i64, top as i64);
+                let mut color = Color::zero();
+                for (j, y_weight) in wy.iter().enumerate() {
+                    for (i, x_weight) in wx.iter().enumerate() {
+                        color = color
+                            + texel(left + i as i64 - 1, top + j as i64 - 1)
+                                * (x_weight * y_weight);
+                    }
+                }
+                color
+            }
+        }
+    }
+}
+
+/// Catmull-Rom weights of the 4 texels around a sample at `t` in [0, 1] past the second one.
+fn catmull_rom(t: f64) -> [f64; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+// Pictures are compared by identity, comparing their texels would slow down meshes
+impl PartialEq for ImageTexture {
+    fn eq(&self, other: &ImageTexture) -> bool {
+        Arc::ptr_eq(&self.image, &other.image)
+            && self.filter == other.filter
+            && self.addressing == other.addressing
+            && self.parameter == other.parameter
+            && self.scale == other.scale
+            && self.base == other.base
+    }
+}
+
+impl Texturable for ImageTexture {
+    fn value(&self, u: f64, v: f64) -> Material {
+        let mut material = self.base.value(u, v);
+        let color = self.sample(u, v);
+        match self.parameter {
+            TextureParameter::Color => material.color = color,
+            TextureParameter::Ambient => material.ambient = color.into(),
+            TextureParameter::Diffuse => material.diffuse = color.into(),
+            TextureParameter::Specular => material.specular = color.into(),
+            TextureParameter::Reflection => material.reflection = color.into(),
+            TextureParameter::Transparency => material.transparency = color.into(),
+        }
+        material
+    }
+}
+
+impl From<ImageTexture> for Texture {
+    fn from(texture: ImageTexture) -> Texture {
+        Texture::Image(texture)
+    }
+}
@@ -1,18 +1,23 @@
-use self::{checkerboard::CheckerboardTexture, uniform::UniformTexture};
+use self::{
+    checkerboard::CheckerboardTexture, image_texture::ImageTexture, uniform::UniformTexture,
+};
 
 use super::Material;
 
 pub mod checkerboard;
+pub mod image_texture;
 pub mod uniform;
 
 pub trait Texturable {
-    fn value(self, u: f64, v: f64) -> Material;
+    fn value(&self, u: f64, v: f64) -> Material;
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum Texture {
     Uniform(UniformTexture),
     Checkerboard(CheckerboardTexture),
+    Image(ImageTexture),
 }
 
 impl Texture {
@@ -28,10 +33,11 @@ impl Default for Texture {
 }
 
 impl Texturable for Texture {
-    fn value(self, u: f64, v: f64) -> Material {
+    fn value(&self, u: f64, v: f64) -> Material {
         match self {
             Texture::Uniform(texture) => texture.value(u, v),
             Texture::Checkerboard(texture) => texture.value(u, v),
+            Texture::Image(texture) => texture.value(u, v),
         }
     }
 }
@@ -2,7 +2,7 @@ use crate::material::Material;
 
 use super::{Texturable, Texture};
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct UniformTexture {
     pub material: Material,
 }
@@ -14,7 +14,7 @@ impl UniformTexture {
 }
 
 impl Texturable for UniformTexture {
-    fn value(self, _u: f64, _v: f64) -> Material {
+    fn value(&self, _u: f64, _v: f64) -> Material {
         self.material
     }
 }
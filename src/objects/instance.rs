@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use crate::{
+    engine::{bounding_box::BoundingBox, intersection::Intersection, ray::Ray},
+    material::Material,
+    utils::math::{point::Point, transform::Transform, vector::Vector},
+};
+
+use super::{Intersectable, Object};
+
+/// Shared geometry placed in the scene with its own transform.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub object: Arc<Object>,
+    pub transform: Transform,
+    pub bounding_box: BoundingBox,
+}
+
+impl Instance {
+    pub fn new(object: Arc<Object>, transform: Transform) -> Instance {
+        let bounding_box = object.bounding_box().transform(&transform);
+        Instance {
+            object,
+            transform,
+            bounding_box,
+        }
+    }
+
+    pub fn with_object(&self, object: Arc<Object>) -> Instance {
+        Instance::new(object, self.transform)
+    }
+
+    pub fn with_transform(&self, transform: Transform) -> Instance {
+        Instance::new(self.object.clone(), transform)
+    }
+
+    /// `ray` in object space, with the factor converting its distances to object space ones since
+    /// the local ray is normalized.
+    fn local_ray(&self, ray: &Ray) -> (Ray, f64) {
+        let inverse = self.transform.inverse();
+        let direction = inverse.vector(&ray.direction);
+        let scale = direction.length();
+        (
+            Ray::new(inverse.point(&ray.origin), direction, ray.depth),
+            scale,
+        )
+    }
+}
+
+impl Intersectable for Instance {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        let (local, scale) = self.local_ray(ray);
+        let mut intersection = self.object.intersect(&local)?;
+        intersection.distance /= scale;
+        intersection.point = ray.point_at(intersection.distance);
+        intersection.normal = self.transform.normal(&intersection.normal);
+        intersection.geometric_normal = self.transform.normal(&intersection.geometric_normal);
+        Some(intersection)
+    }
+
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool {
+        let (local, scale) = self.local_ray(ray);
+        self.object.hits(&local, max_distance * scale)
+    }
+
+    fn normal(&self, point: &Point) -> Vector {
+        let local = self.transform.inverse().point(point);
+        self.transform.normal(&self.object.normal(&local))
+    }
+
+    fn material_at(&self, point: &Point) -> Material {
+        self.object
+            .material_at(&self.transform.inverse().point(point))
+    }
+
+    fn bounding_box(&self) -> BoundingBox {
+        self.bounding_box.clone()
+    }
+}
+
+impl From<Instance> for Object {
+    fn from(instance: Instance) -> Object {
+        Object::Instance(instance)
+    }
+}
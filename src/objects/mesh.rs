@@ -1,32 +1,138 @@
 use crate::{
-    engine::{bounding_box::BoundingBox, intersection::Intersection, ray::Ray},
-    material::Material,
+    engine::{bounding_box::BoundingBox, bvh::Bvh, intersection::Intersection, ray::Ray},
+    material::{texture::Texture, Material},
     utils::math::{point::Point, vector::Vector},
 };
+use std::io::Write;
 
-use super::{Intersectable, Object};
+use super::{smooth_triangle::SmoothTriangle, triangle::Triangle, Intersectable, Object};
 
+/// A triangle of a mesh, as indices in the buffers of the mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    pub positions: [u32; 3],
+    /// Vertex normals, the face is flat shaded without them.
+    pub normals: Option<[u32; 3]>,
+    pub uvs: Option<[u32; 3]>,
+    /// Index in the textures of the mesh.
+    pub texture: u32,
+}
+
+impl Face {
+    pub fn new(positions: [u32; 3]) -> Face {
+        Face {
+            positions,
+            normals: None,
+            uvs: None,
+            texture: 0,
+        }
+    }
+
+    pub fn with_normals(&self, normals: [u32; 3]) -> Face {
+        Face {
+            normals: Some(normals),
+            ..*self
+        }
+    }
+
+    pub fn with_uvs(&self, uvs: [u32; 3]) -> Face {
+        Face {
+            uvs: Some(uvs),
+            ..*self
+        }
+    }
+
+    pub fn with_texture(&self, texture: u32) -> Face {
+        Face { texture, ..*self }
+    }
+}
+
+/// Indexed triangle mesh, triangles are only built when they are hit.
 #[derive(Debug, Clone)]
 pub struct Mesh {
-    pub faces: Vec<Object>,
+    pub positions: Vec<Point>,
+    pub normals: Vec<Vector>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<Face>,
+    pub textures: Vec<Texture>,
     pub bounding_box: BoundingBox,
+    pub bvh: Bvh,
 }
 
 impl Mesh {
-    pub fn new(faces: Vec<Object>) -> Mesh {
-        let bounding_box = BoundingBox::from_objects(&faces);
+    pub fn new(
+        positions: Vec<Point>,
+        normals: Vec<Vector>,
+        uvs: Vec<(f64, f64)>,
+        faces: Vec<Face>,
+        textures: Vec<Texture>,
+    ) -> Mesh {
         Mesh {
+            positions,
+            normals,
+            uvs,
             faces,
-            bounding_box,
+            textures,
+            bounding_box: BoundingBox::empty(),
+            bvh: Bvh::default(),
+        }
+        .build_bvh()
+    }
+
+    /// Collects the triangles, smooth triangles and meshes of `objects` into one mesh, other
+    /// objects are ignored.
+    pub fn from_objects(objects: &[Object]) -> Mesh {
+        let mut mesh = Mesh::default();
+        for object in objects {
+            match object {
+                Object::Triangle(triangle) => {
+                    let mut face = Face::new(mesh.push_positions(&triangle.points))
+                        .with_texture(mesh.push_texture(triangle.texture.clone()));
+                    if let Some(uvs) = triangle.uvs {
+                        face = face.with_uvs(mesh.push_uvs(&uvs));
+                    }
+                    mesh.faces.push(face);
+                }
+                Object::SmoothTriangle(triangle) => {
+                    let normals = [
+                        triangle.normals[0],
+                        triangle.normals[1],
+                        triangle.normals[2],
+                    ];
+                    let mut face = Face::new(mesh.push_positions(&triangle.points))
+                        .with_normals(mesh.push_normals(&normals))
+                        .with_texture(mesh.push_texture(triangle.texture.clone()));
+                    if let Some(uvs) = triangle.uvs {
+                        face = face.with_uvs(mesh.push_uvs(&uvs));
+                    }
+                    mesh.faces.push(face);
+                }
+                Object::Mesh(other) => mesh.append(other),
+                _ => {}
+            }
         }
+        mesh.build_bvh()
     }
 
-    pub fn with_faces(&self, faces: Vec<Object>) -> Mesh {
-        let bounding_box = BoundingBox::from_objects(&faces);
+    pub fn with_faces(&self, faces: Vec<Face>) -> Mesh {
         Mesh {
             faces,
-            bounding_box,
+            ..self.clone()
         }
+        .build_bvh()
+    }
+
+    pub fn with_face(&self, face: Face) -> Mesh {
+        let mut faces = self.faces.clone();
+        faces.push(face);
+        self.with_faces(faces)
+    }
+
+    /// Appends the buffers and faces of `other`.
+    pub fn with_mesh(&self, other: &Mesh) -> Mesh {
+        let mut mesh = self.clone();
+        mesh.append(other);
+        mesh.build_bvh()
     }
 
     pub fn with_bounding_box(&self, bounding_box: BoundingBox) -> Mesh {
@@ -36,44 +142,169 @@ impl Mesh {
         }
     }
 
-    pub fn with_face(&self, face: Object) -> Mesh {
-        let mut faces = self.faces.clone();
-        faces.push(face);
-        let bounding_box = BoundingBox::from_objects(&faces);
+    /// Uses `texture` for every face.
+    pub fn with_texture(&self, texture: Texture) -> Mesh {
         Mesh {
-            faces,
+            faces: self.faces.iter().map(|face| face.with_texture(0)).collect(),
+            textures: vec![texture],
+            ..self.clone()
+        }
+    }
+
+    /// Builds the triangle of the face at `index`.
+    pub fn triangle(&self, index: usize) -> Object {
+        let face = &self.faces[index];
+        let points = self.points(face);
+        let texture = self
+            .textures
+            .get(face.texture as usize)
+            .cloned()
+            .unwrap_or_default();
+        let uvs = face.uvs.map(|uvs| uvs.map(|i| self.uvs[i as usize]));
+        match face.normals {
+            Some(normals) => {
+                let mut triangle =
+                    SmoothTriangle::new(points, normals.map(|i| self.normals[i as usize]), texture);
+                triangle.uvs = uvs;
+                triangle.into()
+            }
+            None => Triangle {
+                points,
+                uvs,
+                texture,
+            }
+            .into(),
+        }
+    }
+
+    fn points(&self, face: &Face) -> [Point; 3] {
+        face.positions.map(|i| self.positions[i as usize])
+    }
+
+    fn push_positions(&mut self, points: &[Point; 3]) -> [u32; 3] {
+        let first = self.positions.len() as u32;
+        self.positions.extend_from_slice(points);
+        [first, first + 1, first + 2]
+    }
+
+    fn push_normals(&mut self, normals: &[Vector; 3]) -> [u32; 3] {
+        let first = self.normals.len() as u32;
+        self.normals.extend_from_slice(normals);
+        [first, first + 1, first + 2]
+    }
+
+    fn push_uvs(&mut self, uvs: &[(f64, f64); 3]) -> [u32; 3] {
+        let first = self.uvs.len() as u32;
+        self.uvs.extend_from_slice(uvs);
+        [first, first + 1, first + 2]
+    }
+
+    fn push_texture(&mut self, texture: Texture) -> u32 {
+        match self.textures.iter().position(|t| *t == texture) {
+            Some(index) => index as u32,
+            None => {
+                self.textures.push(texture);
+                (self.textures.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Appends the buffers and faces of `other` without rebuilding the BVH, see `build_bvh`.
+    pub fn append(&mut self, other: &Mesh) {
+        let positions = self.positions.len() as u32;
+        let normals = self.normals.len() as u32;
+        let uvs = self.uvs.len() as u32;
+        let textures = other
+            .textures
+            .iter()
+            .map(|texture| self.push_texture(texture.clone()))
+            .collect::<Vec<_>>();
+
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.faces.extend(other.faces.iter().map(|face| {
+            Face {
+                positions: face.positions.map(|i| i + positions),
+                normals: face.normals.map(|n| n.map(|i| i + normals)),
+                uvs: face.uvs.map(|uv| uv.map(|i| i + uvs)),
+                texture: textures
+                    .get(face.texture as usize)
+                    .cloned()
+                    .unwrap_or_default(),
+            }
+        }));
+    }
+
+    /// Computes the bounding box and the BVH of the faces.
+    pub fn build_bvh(self) -> Mesh {
+        let boxes = self
+            .faces
+            .iter()
+            .map(|face| BoundingBox::from_points(&self.points(face)))
+            .collect::<Vec<_>>();
+        let bounding_box = boxes
+            .iter()
+            .fold(BoundingBox::empty(), |acc, b| acc.union(b));
+        Mesh {
+            bvh: Bvh::new(&boxes),
             bounding_box,
+            ..self
         }
     }
 
+    /// Writes the mesh with relative indices, so that several objects can share a file.
     pub fn write_to_obj(&self, file: &mut std::fs::File) {
-        self.faces.iter().for_each(|face| match face {
-            Object::Triangle(triangle) => triangle.write_to_obj(file),
-            Object::SmoothTriangle(smooth_triangle) => smooth_triangle.write_to_obj(file),
-            Object::Mesh(mesh) => {
-                mesh.write_to_obj(file);
-            }
-            _ => {}
-        });
+        for point in self.positions.iter() {
+            writeln!(file, "v {} {} {}", point.x, point.y, point.z).unwrap();
+        }
+        for (u, v) in self.uvs.iter() {
+            writeln!(file, "vt {} {}", u, v).unwrap();
+        }
+        for normal in self.normals.iter() {
+            writeln!(file, "vn {} {} {}", normal.x, normal.y, normal.z).unwrap();
+        }
+
+        let relative = |index: u32, count: usize| index as i64 - count as i64;
+        for face in self.faces.iter() {
+            let corners = (0..3).map(|i| {
+                let position = relative(face.positions[i], self.positions.len());
+                match (face.uvs, face.normals) {
+                    (None, None) => format!("{}", position),
+                    (Some(uvs), None) => {
+                        format!("{}/{}", position, relative(uvs[i], self.uvs.len()))
+                    }
+                    (None, Some(normals)) => {
+                        format!("{}//{}", position, relative(normals[i], self.normals.len()))
+                    }
+                    (Some(uvs), Some(normals)) => format!(
+                        "{}/{}/{}",
+                        position,
+                        relative(uvs[i], self.uvs.len()),
+                        relative(normals[i], self.normals.len())
+                    ),
+                }
+            });
+            writeln!(file, "f {}", corners.collect::<Vec<_>>().join(" ")).unwrap();
+        }
     }
 }
 
 impl Intersectable for Mesh {
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        self.faces.iter().fold(None, |inter, face| {
-            let face_inter = face.intersect(ray);
-            match (inter, face_inter) {
-                (None, None) => None,
-                (Some(inter), None) => Some(inter),
-                (None, Some(inter)) => Some(inter),
-                (Some(inter1), Some(inter2)) => {
-                    if inter1.distance < inter2.distance {
-                        Some(inter1)
-                    } else {
-                        Some(inter2)
-                    }
-                }
-            }
+        let (distance, index) = self.bvh.nearest(ray, |index| {
+            Triangle::hit_distance(&self.points(&self.faces[index]), ray)
+                .map(|distance| (distance, index))
+        })?;
+        let mut intersection = Intersection::new(distance, ray, self.triangle(index));
+        intersection.texture = self.faces[index].texture;
+        Some(intersection)
+    }
+
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool {
+        self.bvh.any_hit(ray, max_distance, |index| {
+            Triangle::hit_distance(&self.points(&self.faces[index]), ray)
+                .is_some_and(|distance| distance < max_distance)
         })
     }
 
@@ -86,15 +317,20 @@ impl Intersectable for Mesh {
     }
 
     fn bounding_box(&self) -> BoundingBox {
-        unimplemented!()
+        self.bounding_box.clone()
     }
 }
 
 impl Default for Mesh {
     fn default() -> Mesh {
         Mesh {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
             faces: Vec::new(),
+            textures: Vec::new(),
             bounding_box: BoundingBox::empty(),
+            bvh: Bvh::default(),
         }
     }
 }
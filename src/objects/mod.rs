@@ -1,16 +1,20 @@
 use crate::{
     engine::{bounding_box::BoundingBox, intersection::Intersection, ray::Ray},
-    material::Material,
+    material::{texture::Texture, Material},
     utils::math::{point::Point, vector::Vector},
 };
 
 pub trait Intersectable {
     fn intersect(&self, ray: &Ray) -> Option<Intersection>;
+    /// Whether `ray` hits the surface before travelling `max_distance`, without building the
+    /// intersection.
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool;
     fn normal(&self, point: &Point) -> Vector;
     fn material_at(&self, point: &Point) -> Material;
     fn bounding_box(&self) -> BoundingBox;
 }
 
+pub mod instance;
 pub mod mesh;
 pub mod plane;
 pub mod smooth_triangle;
@@ -24,6 +28,22 @@ pub enum Object {
     Triangle(triangle::Triangle),
     SmoothTriangle(smooth_triangle::SmoothTriangle),
     Mesh(mesh::Mesh),
+    Instance(instance::Instance),
+}
+
+impl Object {
+    /// Textures of the object, indexed by `Intersection::texture`. Meshes have one per material
+    /// and instances their object's.
+    pub fn textures(&self) -> Vec<&Texture> {
+        match self {
+            Object::Sphere(sphere) => vec![&sphere.texture],
+            Object::Plane(plane) => vec![&plane.texture],
+            Object::Triangle(triangle) => vec![&triangle.texture],
+            Object::SmoothTriangle(smooth_triangle) => vec![&smooth_triangle.texture],
+            Object::Mesh(mesh) => mesh.textures.iter().collect(),
+            Object::Instance(instance) => instance.object.textures(),
+        }
+    }
 }
 
 impl Intersectable for Object {
@@ -34,6 +54,18 @@ impl Intersectable for Object {
             Object::Triangle(triangle) => triangle.intersect(ray),
             Object::SmoothTriangle(smooth_triangle) => smooth_triangle.intersect(ray),
             Object::Mesh(mesh) => mesh.intersect(ray),
+            Object::Instance(instance) => instance.intersect(ray),
+        }
+    }
+
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool {
+        match self {
+            Object::Sphere(sphere) => sphere.hits(ray, max_distance),
+            Object::Plane(plane) => plane.hits(ray, max_distance),
+            Object::Triangle(triangle) => triangle.hits(ray, max_distance),
+            Object::SmoothTriangle(smooth_triangle) => smooth_triangle.hits(ray, max_distance),
+            Object::Mesh(mesh) => mesh.hits(ray, max_distance),
+            Object::Instance(instance) => instance.hits(ray, max_distance),
         }
     }
 
@@ -44,6 +76,7 @@ impl Intersectable for Object {
             Object::Triangle(triangle) => triangle.normal(point),
             Object::SmoothTriangle(smooth_triangle) => smooth_triangle.normal(point),
             Object::Mesh(mesh) => mesh.normal(point),
+            Object::Instance(instance) => instance.normal(point),
         }
     }
 
@@ -54,6 +87,7 @@ impl Intersectable for Object {
             Object::Triangle(triangle) => triangle.material_at(point),
             Object::SmoothTriangle(smooth_triangle) => smooth_triangle.material_at(point),
             Object::Mesh(mesh) => mesh.material_at(point),
+            Object::Instance(instance) => instance.material_at(point),
         }
     }
 
@@ -64,6 +98,7 @@ impl Intersectable for Object {
             Object::Triangle(triangle) => triangle.bounding_box(),
             Object::SmoothTriangle(smooth_triangle) => smooth_triangle.bounding_box(),
             Object::Mesh(mesh) => mesh.bounding_box(),
+            Object::Instance(instance) => instance.bounding_box(),
         }
     }
 }
@@ -25,21 +25,55 @@ impl Plane {
             scale,
         }
     }
-}
 
-impl Intersectable for Plane {
-    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+    /// Directions of `u` and `v` on the plane. `v` follows the projection of `y`, or of `x` for
+    /// floors and ceilings, and `u × v` is the normal so that pictures aren't mirrored when seen
+    /// from the front.
+    pub fn tangent_frame(&self) -> (Vector, Vector) {
+        let normal = self.normal.normalize();
+        let axis = if normal.y.abs() < 0.9 {
+            Vector::new(0.0, 1.0, 0.0)
+        } else {
+            Vector::new(1.0, 0.0, 0.0)
+        };
+        let bitangent = (axis - normal * normal.dot(&axis)).normalize();
+        (bitangent.cross(&normal), bitangent)
+    }
+
+    /// Distance to the plane along `ray` when it comes from the front.
+    fn hit_distance(&self, ray: &Ray) -> Option<f64> {
         let denominator = self.normal.dot(&ray.direction);
         if denominator > 0.0 {
             return None;
         }
-        let numerator = self.normal.dot(&(ray.origin - self.position));
+        let numerator = self.normal.dot(&(self.position - ray.origin));
         let t = numerator / denominator;
         if t < 0.0 {
             return None;
         }
-        println!("Plane intersection: {:?}", self.position);
-        Some(Intersection::new(t, ray, self.into()))
+        Some(t)
+    }
+
+    /// Texture coordinates of a point of the surface.
+    pub fn uv(&self, point: &Point) -> (f64, f64) {
+        let (tangent, bitangent) = self.tangent_frame();
+        let offset = *point - self.position;
+        (
+            offset.dot(&tangent) / self.scale,
+            offset.dot(&bitangent) / self.scale,
+        )
+    }
+}
+
+impl Intersectable for Plane {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit_distance(ray)
+            .map(|distance| Intersection::new(distance, ray, self.into()))
+    }
+
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool {
+        self.hit_distance(ray)
+            .is_some_and(|distance| distance < max_distance)
     }
 
     fn normal(&self, _point: &Point) -> Vector {
@@ -47,8 +81,7 @@ impl Intersectable for Plane {
     }
 
     fn material_at(&self, point: &Point) -> Material {
-        let u = (point.x - self.position.x) / self.scale;
-        let v = (point.z - self.position.z) / self.scale;
+        let (u, v) = self.uv(point);
         self.texture.value(u, v)
     }
 
@@ -1,4 +1,4 @@
-use super::{Intersectable, Object};
+use super::{triangle::Triangle, Intersectable, Object};
 use crate::{
     engine::{bounding_box::BoundingBox, intersection::Intersection, ray::Ray},
     material::texture::{Texturable, Texture},
@@ -10,6 +10,8 @@ use std::io::Write;
 pub struct SmoothTriangle {
     pub points: [Point; 3],
     pub normals: Vec<Vector>,
+    /// Texture coordinates of the vertices, (0, 0), (1, 0) and (0, 1) without them.
+    pub uvs: Option<[(f64, f64); 3]>,
     pub texture: Texture,
 }
 
@@ -18,6 +20,7 @@ impl SmoothTriangle {
         SmoothTriangle {
             points,
             normals: normals.to_vec(),
+            uvs: None,
             texture,
         }
     }
@@ -43,6 +46,13 @@ impl SmoothTriangle {
         }
     }
 
+    pub fn with_uvs(&self, uvs: [(f64, f64); 3]) -> SmoothTriangle {
+        SmoothTriangle {
+            uvs: Some(uvs),
+            ..self.clone()
+        }
+    }
+
     pub fn with_a(&self, a: Point, a_n: Vector) -> SmoothTriangle {
         let mut points = self.points;
         points[0] = a;
@@ -79,6 +89,22 @@ impl SmoothTriangle {
         }
     }
 
+    /// Normal of the flat triangle, on the side of the vertex normals.
+    pub fn face_normal(&self) -> Vector {
+        let e1 = self.points[1] - self.points[0];
+        let e2 = self.points[2] - self.points[0];
+        let normal = e1.cross(&e2).normalize();
+        let average = self
+            .normals
+            .iter()
+            .fold(Vector::new(0.0, 0.0, 0.0), |sum, n| sum + *n);
+        if normal.dot(&average) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    }
+
     pub fn write_to_obj(&self, file: &mut std::fs::File) {
         for point in self.points.iter() {
             writeln!(file, "v {} {} {}", point.x, point.y, point.z).unwrap();
@@ -86,75 +112,42 @@ impl SmoothTriangle {
         for normal in self.normals.iter() {
             writeln!(file, "vn {} {} {}", normal.x, normal.y, normal.z).unwrap();
         }
-        writeln!(file, "f -1//-1 -2//-2 -3//-3").unwrap();
+        match self.uvs {
+            Some(uvs) => {
+                for (u, v) in uvs.iter() {
+                    writeln!(file, "vt {} {}", u, v).unwrap();
+                }
+                writeln!(file, "f -1/-1/-1 -2/-2/-2 -3/-3/-3").unwrap();
+            }
+            None => writeln!(file, "f -1//-1 -2//-2 -3//-3").unwrap(),
+        }
+    }
+
+    /// Texture coordinates of a point of the surface.
+    pub fn uv(&self, point: &Point) -> (f64, f64) {
+        Triangle::interpolate_uv(&self.points, self.uvs, point)
     }
 }
 
 impl Intersectable for SmoothTriangle {
     fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        let e1 = self.points[1] - self.points[0];
-        let e2 = self.points[2] - self.points[0];
-        let p = ray.direction.cross(&e2);
-
-        let det = e1.dot(&p);
-        if det.abs() < 1e-8 {
-            return None;
-        }
-
-        let inv_det = 1.0 / det;
-        let t = ray.origin - self.points[0];
-        let u = t.dot(&p) * inv_det;
-        if !(0.0..=1.0).contains(&u) {
-            return None;
-        }
-
-        let q = t.cross(&e1);
-        let v = ray.direction.dot(&q) * inv_det;
-        if v < 0.0 || u + v > 1.0 {
-            return None;
-        }
-
-        let t = e2.dot(&q) * inv_det;
-        if t < 0.0 {
-            return None;
-        }
+        Triangle::hit_distance(&self.points, ray)
+            .map(|distance| Intersection::new(distance, ray, self.clone().into()))
+    }
 
-        Some(Intersection::new(t, ray, self.clone().into()))
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool {
+        Triangle::hit_distance(&self.points, ray).is_some_and(|distance| distance < max_distance)
     }
 
     fn normal(&self, point: &Point) -> Vector {
         // Compute normal using Pheng's method
-        let e1 = self.points[1] - self.points[0];
-        let e2 = self.points[2] - self.points[0];
-        let p = *point - self.points[0];
-        let d00 = e1.dot(&e1);
-        let d01 = e1.dot(&e2);
-        let d11 = e2.dot(&e2);
-        let d20 = p.dot(&e1);
-        let d21 = p.dot(&e2);
-        let denom = d00 * d11 - d01 * d01;
-        let v = (d11 * d20 - d01 * d21) / denom;
-        let w = (d00 * d21 - d01 * d20) / denom;
-        let u = 1.0 - v - w;
+        let [u, v, w] = Triangle::barycentric(&self.points, point);
         let normal = self.normals[0] * u + self.normals[1] * v + self.normals[2] * w;
         normal.normalize()
     }
 
     fn material_at(&self, point: &Point) -> crate::material::Material {
-        // Get uv coods at point on triangle
-        let e1 = self.points[1] - self.points[0];
-        let e2 = self.points[2] - self.points[0];
-        let p = *point - self.points[0];
-        let d00 = e1.dot(&e1);
-        let d01 = e1.dot(&e2);
-        let d11 = e2.dot(&e2);
-        let d20 = p.dot(&e1);
-        let d21 = p.dot(&e2);
-        let denom = d00 * d11 - d01 * d01;
-        let v = (d11 * d20 - d01 * d21) / denom;
-        let w = (d00 * d21 - d01 * d20) / denom;
-        let u = 1.0 - v - w;
-        // Get material at point on triangle
+        let (u, v) = self.uv(point);
         self.texture.value(u, v)
     }
 
@@ -186,6 +179,7 @@ impl Default for SmoothTriangle {
                 Vector::new(0.0, 0.0, 0.0),
                 Vector::new(0.0, 0.0, 0.0),
             ],
+            uvs: None,
             texture: Texture::default(),
         }
     }
@@ -42,10 +42,9 @@ impl Sphere {
         sphere.radius = radius;
         sphere
     }
-}
 
-impl Intersectable for Sphere {
-    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+    /// Distance to the closest hit in front of the origin of `ray`.
+    fn hit_distance(&self, ray: &Ray) -> Option<f64> {
         let oc = ray.origin - self.center;
         let a = ray.direction.dot(&ray.direction);
         let b = 2.0 * oc.dot(&ray.direction);
@@ -71,20 +70,37 @@ impl Intersectable for Sphere {
             t1.min(t2)
         };
 
-        Some(Intersection::new(t, ray, self.into()))
+        Some(t)
     }
 
-    fn normal(&self, point: &Point) -> Vector {
-        (*point - self.center).normalize()
-    }
-
-    fn material_at(&self, point: &Point) -> Material {
+    /// Spherical mapping, `u` turns around `y` and `v` goes from the bottom to the top.
+    pub fn uv(&self, point: &Point) -> (f64, f64) {
         let normal = self.normal(point);
         let phi = normal.z.atan2(normal.x);
         let theta = normal.y.asin();
         let u = 1.0 - (phi + std::f64::consts::PI) / (2.0 * std::f64::consts::PI);
         let v = (theta + std::f64::consts::FRAC_PI_2) / std::f64::consts::PI;
+        (u, v)
+    }
+}
 
+impl Intersectable for Sphere {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        self.hit_distance(ray)
+            .map(|distance| Intersection::new(distance, ray, self.into()))
+    }
+
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool {
+        self.hit_distance(ray)
+            .is_some_and(|distance| distance < max_distance)
+    }
+
+    fn normal(&self, point: &Point) -> Vector {
+        (*point - self.center).normalize()
+    }
+
+    fn material_at(&self, point: &Point) -> Material {
+        let (u, v) = self.uv(point);
         self.texture.value(u, v)
     }
 
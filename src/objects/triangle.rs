@@ -12,12 +12,18 @@ use std::io::Write;
 #[derive(Debug, Clone)]
 pub struct Triangle {
     pub points: [Point; 3],
+    /// Texture coordinates of the vertices, (0, 0), (1, 0) and (0, 1) without them.
+    pub uvs: Option<[(f64, f64); 3]>,
     pub texture: Texture,
 }
 
 impl Triangle {
     pub fn new(points: [Point; 3], texture: Texture) -> Triangle {
-        Triangle { points, texture }
+        Triangle {
+            points,
+            uvs: None,
+            texture,
+        }
     }
 
     pub fn with_points(&self, points: [Point; 3]) -> Triangle {
@@ -61,25 +67,24 @@ impl Triangle {
         }
     }
 
-    pub fn write_to_obj(&self, file: &mut std::fs::File) {
-        for point in self.points.iter() {
-            writeln!(file, "v {} {} {}", point.x, point.y, point.z).unwrap();
+    pub fn with_uvs(&self, uvs: [(f64, f64); 3]) -> Triangle {
+        Triangle {
+            uvs: Some(uvs),
+            ..self.clone()
         }
-        writeln!(file, "f {} {} {}", -3, -2, -1).unwrap();
     }
-}
 
-impl Intersectable for Triangle {
-    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
-        let e1 = self.points[1] - self.points[0];
-        let e2 = self.points[2] - self.points[0];
+    /// Möller–Trumbore intersection, shared by every triangle primitive.
+    pub fn hit_distance(points: &[Point; 3], ray: &Ray) -> Option<f64> {
+        let e1 = points[1] - points[0];
+        let e2 = points[2] - points[0];
         let p = ray.direction.cross(&e2);
         let a = e1.dot(&p);
-        if a.abs() < 1e-6 {
+        if a.abs() < 1e-12 {
             return None;
         }
         let f = 1.0 / a;
-        let s = ray.origin - self.points[0];
+        let s = ray.origin - points[0];
         let u = f * s.dot(&p);
         if !(0.0..=1.0).contains(&u) {
             return None;
@@ -90,13 +95,72 @@ impl Intersectable for Triangle {
             return None;
         }
         let t = f * e2.dot(&q);
-        if t > 1e-6 {
-            Some(Intersection::new(t, ray, (self.clone()).into()))
-        } else {
-            None
+        (t > 1e-6).then_some(t)
+    }
+
+    /// Barycentric coordinates of `point` in the triangle, the weights of its three vertices.
+    pub fn barycentric(points: &[Point; 3], point: &Point) -> [f64; 3] {
+        let e1 = points[1] - points[0];
+        let e2 = points[2] - points[0];
+        let p = *point - points[0];
+        let d00 = e1.dot(&e1);
+        let d01 = e1.dot(&e2);
+        let d11 = e2.dot(&e2);
+        let d20 = p.dot(&e1);
+        let d21 = p.dot(&e2);
+        let denom = d00 * d11 - d01 * d01;
+        let v = (d11 * d20 - d01 * d21) / denom;
+        let w = (d00 * d21 - d01 * d20) / denom;
+        [1.0 - v - w, v, w]
+    }
+
+    /// Texture coordinates at `point`, interpolated from the ones of the vertices.
+    pub fn interpolate_uv(
+        points: &[Point; 3],
+        uvs: Option<[(f64, f64); 3]>,
+        point: &Point,
+    ) -> (f64, f64) {
+        let weights = Triangle::barycentric(points, point);
+        match uvs {
+            Some(uvs) => (
+                uvs[0].0 * weights[0] + uvs[1].0 * weights[1] + uvs[2].0 * weights[2],
+                uvs[0].1 * weights[0] + uvs[1].1 * weights[1] + uvs[2].1 * weights[2],
+            ),
+            None => (weights[1], weights[2]),
         }
     }
 
+    pub fn write_to_obj(&self, file: &mut std::fs::File) {
+        for point in self.points.iter() {
+            writeln!(file, "v {} {} {}", point.x, point.y, point.z).unwrap();
+        }
+        match self.uvs {
+            Some(uvs) => {
+                for (u, v) in uvs.iter() {
+                    writeln!(file, "vt {} {}", u, v).unwrap();
+                }
+                writeln!(file, "f -3/-3 -2/-2 -1/-1").unwrap();
+            }
+            None => writeln!(file, "f {} {} {}", -3, -2, -1).unwrap(),
+        }
+    }
+
+    /// Texture coordinates of a point of the surface.
+    pub fn uv(&self, point: &Point) -> (f64, f64) {
+        Triangle::interpolate_uv(&self.points, self.uvs, point)
+    }
+}
+
+impl Intersectable for Triangle {
+    fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+        Triangle::hit_distance(&self.points, ray)
+            .map(|distance| Intersection::new(distance, ray, self.clone().into()))
+    }
+
+    fn hits(&self, ray: &Ray, max_distance: f64) -> bool {
+        Triangle::hit_distance(&self.points, ray).is_some_and(|distance| distance < max_distance)
+    }
+
     fn normal(&self, _point: &Point) -> Vector {
         let e1 = self.points[1] - self.points[0];
         let e2 = self.points[2] - self.points[0];
@@ -104,13 +168,7 @@ impl Intersectable for Triangle {
     }
 
     fn material_at(&self, point: &Point) -> Material {
-        // Find uv coords in triangle
-        let e1 = self.points[1] - self.points[0];
-        let e2 = self.points[2] - self.points[0];
-        let p = *point - self.points[0];
-        let u = e1.dot(&p) / e1.dot(&e1);
-        let v = e2.dot(&p) / e2.dot(&e2);
-
+        let (u, v) = self.uv(point);
         self.texture.value(u, v)
     }
 
@@ -138,6 +196,7 @@ impl Default for Triangle {
                 Point::new(0.0, 0.0, 0.0),
                 Point::new(0.0, 0.0, 0.0),
             ],
+            uvs: None,
             texture: Texture::default(),
         }
     }
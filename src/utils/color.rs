@@ -2,7 +2,7 @@ use std::ops::{Add, Div, Mul, Sub};
 
 use image::Rgb;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: f64,
     pub g: f64,
@@ -32,9 +32,14 @@ impl Color {
         }
     }
 
+    pub fn max_component(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// 8-bit gamma corrected color, components are clamped to [0, 1] first.
     pub fn to_rgb(&self) -> (u8, u8, u8) {
         fn gamma_correct(color: f64) -> f64 {
-            color.powf(1.0 / Color::GAMMA)
+            color.clamp(0.0, 1.0).powf(1.0 / Color::GAMMA)
         }
 
         (
@@ -54,11 +59,18 @@ impl Color {
     }
 
     pub fn from_hex(hex: &str) -> Color {
+        Color::try_from_hex(hex).unwrap()
+    }
+
+    pub fn try_from_hex(hex: &str) -> Option<Color> {
         let hex = hex.trim_start_matches('#');
-        let r = u8::from_str_radix(&hex[0..2], 16).unwrap();
-        let g = u8::from_str_radix(&hex[2..4], 16).unwrap();
-        let b = u8::from_str_radix(&hex[4..6], 16).unwrap();
-        Color::from((r, g, b))
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
+        let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
+        let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
+        Some(Color::from((r, g, b)))
     }
 
     pub fn relative_luminance(&self) -> f64 {
@@ -2,7 +2,7 @@ use std::ops::{Add, Mul, Sub};
 
 use super::color::Color;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ColorOrFloat {
     Color(Color),
     Float(f64),
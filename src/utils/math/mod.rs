@@ -1,2 +1,21 @@
 pub mod point;
+pub mod transform;
 pub mod vector;
+
+/// Maps two uniform numbers in [0, 1) to a uniformly distributed point of the unit disk.
+///
+/// Shirley-Chiu concentric mapping, it keeps stratified samples well spread on the disk.
+pub fn concentric_disk(u: f64, v: f64) -> (f64, f64) {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    let (r, phi) = if a == 0.0 && b == 0.0 {
+        (0.0, 0.0)
+    } else if a.abs() > b.abs() {
+        (a, std::f64::consts::FRAC_PI_4 * (b / a))
+    } else {
+        (
+            b,
+            std::f64::consts::FRAC_PI_2 - std::f64::consts::FRAC_PI_4 * (a / b),
+        )
+    };
+    (r * phi.cos(), r * phi.sin())
+}
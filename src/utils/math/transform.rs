@@ -0,0 +1,254 @@
+use super::{point::Point, vector::Vector};
+
+pub type Matrix = [[f64; 4]; 4];
+
+/// Affine transform stored as a 4x4 matrix along with its inverse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub matrix: Matrix,
+    pub inverse: Matrix,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: IDENTITY,
+            inverse: IDENTITY,
+        }
+    }
+
+    /// Returns `None` if the matrix is not invertible.
+    pub fn from_matrix(matrix: Matrix) -> Option<Transform> {
+        Some(Transform {
+            matrix,
+            inverse: invert(&matrix)?,
+        })
+    }
+
+    pub fn translation(offset: Vector) -> Transform {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for (i, value) in <[f64; 3]>::from(offset).into_iter().enumerate() {
+            matrix[i][3] = value;
+            inverse[i][3] = -value;
+        }
+        Transform { matrix, inverse }
+    }
+
+    /// Scales along each axis, factors must not be zero.
+    pub fn scaling(x: f64, y: f64, z: f64) -> Transform {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for (i, value) in [x, y, z].into_iter().enumerate() {
+            matrix[i][i] = value;
+            inverse[i][i] = 1.0 / value;
+        }
+        Transform { matrix, inverse }
+    }
+
+    /// Rotation of `angle` degrees around `axis`, counter-clockwise when looking down the axis.
+    pub fn rotation(axis: Vector, angle: f64) -> Transform {
+        let axis = axis.normalize();
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (x, y, z) = (axis.x, axis.y, axis.z);
+        let t = 1.0 - cos;
+
+        let matrix = [
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // Rotations are orthogonal, the inverse is the transpose
+        Transform {
+            matrix,
+            inverse: transpose(&matrix),
+        }
+    }
+
+    /// Applies `self` then `other`.
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            matrix: multiply(&other.matrix, &self.matrix),
+            inverse: multiply(&self.inverse, &other.inverse),
+        }
+    }
+
+    pub fn with_translation(&self, offset: Vector) -> Transform {
+        self.then(&Transform::translation(offset))
+    }
+
+    pub fn with_scaling(&self, x: f64, y: f64, z: f64) -> Transform {
+        self.then(&Transform::scaling(x, y, z))
+    }
+
+    pub fn with_rotation(&self, axis: Vector, angle: f64) -> Transform {
+        self.then(&Transform::rotation(axis, angle))
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, point: &Point) -> Point {
+        let m = &self.matrix;
+        Point::new(
+            m[0][0] * point.x + m[0][1] * point.y + m[0][2] * point.z + m[0][3],
+            m[1][0] * point.x + m[1][1] * point.y + m[1][2] * point.z + m[1][3],
+            m[2][0] * point.x + m[2][1] * point.y + m[2][2] * point.z + m[2][3],
+        )
+    }
+
+    /// Transforms a direction, ignoring the translation.
+    pub fn vector(&self, vector: &Vector) -> Vector {
+        let m = &self.matrix;
+        Vector::new(
+            m[0][0] * vector.x + m[0][1] * vector.y + m[0][2] * vector.z,
+            m[1][0] * vector.x + m[1][1] * vector.y + m[1][2] * vector.z,
+            m[2][0] * vector.x + m[2][1] * vector.y + m[2][2] * vector.z,
+        )
+    }
+
+    /// Transforms a surface normal with the inverse transpose, so that it stays perpendicular to
+    /// the surface under non-uniform scaling. The result is normalized.
+    pub fn normal(&self, normal: &Vector) -> Vector {
+        let m = &self.inverse;
+        Vector::new(
+            m[0][0] * normal.x + m[1][0] * normal.y + m[2][0] * normal.z,
+            m[0][1] * normal.x + m[1][1] * normal.y + m[2][1] * normal.z,
+            m[0][2] * normal.x + m[1][2] * normal.y + m[2][2] * normal.z,
+        )
+        .normalize()
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 4]; 4];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    result
+}
+
+fn transpose(matrix: &Matrix) -> Matrix {
+    let mut result = [[0.0; 4]; 4];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = matrix[j][i];
+        }
+    }
+    result
+}
+
+/// Gauss-Jordan elimination with partial pivoting.
+fn invert(matrix: &Matrix) -> Option<Matrix> {
+    let mut m = *matrix;
+    let mut inverse = IDENTITY;
+
+    for column in 0..4 {
+        let pivot =
+            (column..4).max_by(|&a, &b| m[a][column].abs().total_cmp(&m[b][column].abs()))?;
+        if m[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        m.swap(column, pivot);
+        inverse.swap(column, pivot);
+
+        let scale = 1.0 / m[column][column];
+        for j in 0..4 {
+            m[column][j] *= scale;
+            inverse[column][j] *= scale;
+        }
+
+        for row in 0..4 {
+            if row == column {
+                continue;
+            }
+            let factor = m[row][column];
+            for j in 0..4 {
+                m[row][j] -= factor * m[column][j];
+                inverse[row][j] -= factor * inverse[column][j];
+            }
+        }
+    }
+
+    Some(inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &Matrix, b: &Matrix) {
+        for (row_a, row_b) in a.iter().zip(b) {
+            for (x, y) in row_a.iter().zip(row_b) {
+                assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    fn transform() -> Transform {
+        Transform::scaling(2.0, 0.5, -3.0)
+            .with_rotation(Vector::new(1.0, 2.0, 3.0), 37.0)
+            .with_translation(Vector::new(4.0, -5.0, 6.0))
+    }
+
+    #[test]
+    fn inverse_round_trips_points_and_vectors() {
+        let transform = transform();
+        let point = Point::new(1.5, -2.0, 0.25);
+        let vector = Vector::new(-0.5, 3.0, 1.0);
+
+        let back = transform.inverse().point(&transform.point(&point));
+        assert!((back - point).length() < 1e-9);
+        let back = transform.inverse().vector(&transform.vector(&vector));
+        assert!((back - vector).length() < 1e-9);
+    }
+
+    #[test]
+    fn composed_inverse_matches_the_inverted_matrix() {
+        let transform = transform();
+        assert_close(&multiply(&transform.matrix, &transform.inverse), &IDENTITY);
+        assert_close(&invert(&transform.matrix).unwrap(), &transform.inverse);
+    }
+
+    #[test]
+    fn singular_matrices_are_rejected() {
+        let mut matrix = IDENTITY;
+        matrix[1] = matrix[0];
+        assert!(Transform::from_matrix(matrix).is_none());
+    }
+}
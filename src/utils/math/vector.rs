@@ -57,11 +57,52 @@ impl Vector {
         *self - *normal * 2.0 * self.dot(normal)
     }
 
-    pub fn refract(&self, normal: &Vector, etai_over_etat: f64) -> Vector {
-        let cos_theta = (-*self).dot(normal).min(1.0);
-        let r_out_perp = *self + *normal * cos_theta * etai_over_etat;
-        let r_out_parallel = *normal * -(1.0 - r_out_perp.length_squared()).abs().sqrt();
-        r_out_perp + r_out_parallel
+    /// Returns two unit vectors forming an orthonormal basis with `self`, which must be normalized.
+    pub fn orthonormal_basis(&self) -> (Vector, Vector) {
+        // Duff et al., "Building an Orthonormal Basis, Revisited"
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+        (
+            Vector::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Vector::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
+
+    /// Maps two uniform numbers in [0, 1) to a cosine-weighted direction in the hemisphere around `self`.
+    pub fn cosine_hemisphere(&self, u: f64, v: f64) -> Vector {
+        let (tangent, bitangent) = self.orthonormal_basis();
+        let r = u.sqrt();
+        let phi = 2.0 * std::f64::consts::PI * v;
+        let z = (1.0 - u).max(0.0).sqrt();
+        (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + *self * z).normalize()
+    }
+
+    /// Maps two uniform numbers in [0, 1) to a uniformly distributed direction in the cone around
+    /// `self` whose half angle has a cosine of `cos_max`.
+    pub fn uniform_cone(&self, cos_max: f64, u: f64, v: f64) -> Vector {
+        let (tangent, bitangent) = self.orthonormal_basis();
+        let cos_theta = 1.0 - u * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * v;
+        (tangent * (sin_theta * phi.cos())
+            + bitangent * (sin_theta * phi.sin())
+            + *self * cos_theta)
+            .normalize()
+    }
+
+    /// Refracts `self` through a surface whose `normal` points towards the incident side.
+    ///
+    /// `eta` is the ratio of the incident over the transmitted refraction indices, both vectors must
+    /// be normalized. Returns `None` on total internal reflection.
+    pub fn refract(&self, normal: &Vector, eta: f64) -> Option<Vector> {
+        let cos_i = (-*self).dot(normal).min(1.0);
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+        if sin2_t > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(*self * eta + *normal * (eta * cos_i - cos_t))
     }
 }
 